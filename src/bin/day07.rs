@@ -1,7 +1,4 @@
-use std::{cmp::Ordering, collections::HashMap};
-
-use aoc2023::{read_input, InputType};
-use itertools::Itertools;
+use aoc2023::{day07::Ruleset, read_input, InputType};
 use timed::timed;
 
 const DAY: u8 = 7;
@@ -12,136 +9,15 @@ fn main() {
     println!("Part1: {}", part1(&input));
     println!("Part2: {}", part2(&input));
 }
-#[derive(Debug)]
-struct Card {
-    label: char,
-    value: u8,
-}
-impl Card {
-    fn new(label: char) -> Card {
-        Card {
-            label,
-            value: match label {
-                'A' => 14,
-                'K' => 13,
-                'Q' => 12,
-                'J' => 11,
-                'T' => 10,
-                'Y' => 1,
-                _ => label.to_digit(10).unwrap() as u8,
-            },
-        }
-    }
-}
-
-#[derive(Debug)]
-struct Hand {
-    cards: Vec<Card>,
-    value: u8,
-    bid: u32,
-}
-
-impl Hand {
-    fn get_hand_value(
-        joker_count: i32,
-        distinct_cards_count: usize,
-        min_count_of_distinct_cards: i32,
-        max_count_of_distinct_cards: i32,
-    ) -> u8 {
-        let max_count_of_distinct_cards = max_count_of_distinct_cards + joker_count;
-        match (
-            distinct_cards_count,
-            min_count_of_distinct_cards,
-            max_count_of_distinct_cards,
-        ) {
-            (1, _, _) => 7, // Five of a kind
-            (2, _, 4) => 6, // Four of a kind
-            (2, 2, 3) => 5, // Full house
-            (3, _, 3) => 4, // Three of a kind
-            (3, 1, 2) => 3, // Two pair
-            (4, _, 2) => 2, // One pair
-            (5, _, _) => 1, // High card
-            _ => unreachable!(),
-        }
-    }
-    fn new(cards_str: &str, bid: u32) -> Hand {
-        let mut cards = vec![];
-        for card_char in cards_str.chars() {
-            cards.push(Card::new(card_char));
-        }
-        let mut count = cards.iter().fold(HashMap::new(), |mut acc, x| {
-            *acc.entry(x.label).or_insert(0) += 1;
-            acc
-        });
-        let joker_count = *count.get(&'Y').unwrap_or(&0);
-        count.remove(&'Y');
-        let count = count.values().collect::<Vec<_>>();
-        let min_count = count.iter().min().unwrap_or(&&0);
-        let max_count = count.iter().max().unwrap_or(&&0);
-        let value = match joker_count {
-            0..=3 => Hand::get_hand_value(joker_count, count.len(), **min_count, **max_count),
-            _ => 7,
-        };
-
-        Hand { cards, value, bid }
-    }
-}
-impl Ord for Hand {
-    fn cmp(&self, other: &Self) -> Ordering {
-        if self.value.cmp(&other.value) == Ordering::Equal {
-            for i in 0..5 {
-                if self.cards[i].value.cmp(&other.cards[i].value) != Ordering::Equal {
-                    return self.cards[i].value.cmp(&other.cards[i].value);
-                }
-            }
-            unreachable!()
-        } else {
-            self.value.cmp(&other.value)
-        }
-    }
-}
-impl PartialOrd for Hand {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.value.cmp(&other.value))
-    }
-}
-impl PartialEq for Hand {
-    fn eq(&self, other: &Self) -> bool {
-        self.value == other.value
-    }
-}
-impl Eq for Hand {}
 
 #[timed]
-fn part1(input: &str) -> u32 {
-    input
-        .lines()
-        .map(|x| {
-            let mut hand = x.split(' ');
-            Hand::new(
-                hand.next().unwrap(),
-                hand.next().unwrap().parse::<u32>().unwrap(),
-            )
-        })
-        .sorted_by(|a, b| a.cmp(b))
-        .enumerate()
-        .fold(0, |acc, (index, hand)| acc + hand.bid * (index as u32 + 1))
+fn part1(input: &str) -> u64 {
+    Ruleset::standard().winnings(input).unwrap()
 }
 
 #[timed]
-fn part2(input: &str) -> u32 {
-    input
-        .lines()
-        .map(|x| {
-            let mut hand = x.split(' ');
-            Hand::new(
-                &hand.next().unwrap().replace("J", "Y"),
-                hand.next().unwrap().parse::<u32>().unwrap(),
-            )
-        })
-        .sorted_by(|a, b| a.cmp(b))
-        .enumerate()
-        .fold(0, |acc, (index, hand)| acc + hand.bid * (index as u32 + 1))
+fn part2(input: &str) -> u64 {
+    Ruleset::jokers().winnings(input).unwrap()
 }
 
 #[cfg(test)]
@@ -1,27 +1,30 @@
-use std::collections::HashMap;
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt::{self, Display, Formatter},
+};
 
 use aoc2023::{read_input, InputType};
 use timed::timed;
 
 const DAY: u8 = 8;
-const NEWLINE_CHAR: &str = "\r\n";
 
 fn main() {
     let input = read_input(DAY, InputType::Real).unwrap();
 
     println!("Part1: {}", part1(&input));
     println!("Part2 LCM: {}", part2_lcm(&input));
-    println!("Part2 Bruteforce: {}", part2_bruteforce(&input));
+    println!("Part2: {}", part2(&input));
+    if std::env::args().any(|arg| arg == "--cycles") {
+        print!("{}", report(&input));
+    }
 }
 
+/// Accepts both `\n` and `\r\n` line endings.
 fn parse_data(input: &str) -> (Vec<char>, HashMap<&str, (&str, &str)>) {
-    let split_by = NEWLINE_CHAR.repeat(2);
-    let mut lines = input.split(&split_by);
+    let mut lines = input.lines();
     let lr_directions = lines.next().unwrap().chars().collect::<Vec<_>>();
     let node_map = lines
-        .next()
-        .unwrap()
-        .lines()
+        .filter(|line| !line.is_empty())
         .fold(HashMap::new(), |mut acc, line| {
             let (label, lr) = line.split_once(" = ").unwrap();
             let lr: (&str, &str) = lr[1..9].split_once(", ").unwrap();
@@ -125,6 +128,217 @@ fn part2_lcm(input: &str) -> u128 {
         .fold(1, |acc, x| lcm(acc, x))
 }
 
+/// Path of a ghost over (node, direction index) states, which has to repeat eventually.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Ghost<'a> {
+    start: &'a str,
+    /// Steps before the first state of the cycle.
+    prefix: usize,
+    /// Steps in one lap of the cycle.
+    cycle: usize,
+    /// Steps ending on a Z node, up to the end of the first lap.
+    hits: Vec<usize>,
+    instructions: usize,
+}
+impl<'a> Ghost<'a> {
+    fn new(
+        start: &'a str,
+        lr_directions: &[char],
+        node_map: &HashMap<&'a str, (&'a str, &'a str)>,
+    ) -> Ghost<'a> {
+        let mut seen = HashMap::new();
+        let mut hits = vec![];
+        let mut current = start;
+        let mut steps = 0;
+        loop {
+            let direction_index = steps % lr_directions.len();
+            if let Some(&prefix) = seen.get(&(current, direction_index)) {
+                return Ghost {
+                    start,
+                    prefix,
+                    cycle: steps - prefix,
+                    hits,
+                    instructions: lr_directions.len(),
+                };
+            }
+            seen.insert((current, direction_index), steps);
+            if current.ends_with('Z') {
+                hits.push(steps);
+            }
+            let directions = node_map.get(current).unwrap();
+            if lr_directions[direction_index] == 'L' {
+                current = directions.0;
+            } else {
+                current = directions.1;
+            }
+            steps += 1;
+        }
+    }
+
+    /// Hits repeating every lap, as arithmetic progressions.
+    fn cyclic_hits(&self) -> Vec<Progression> {
+        self.hits
+            .iter()
+            .filter(|&&x| x >= self.prefix)
+            .map(|&x| Progression {
+                start: x as i128,
+                step: self.cycle as i128,
+            })
+            .collect()
+    }
+
+    fn hits_at(&self, steps: i128) -> bool {
+        self.hits.iter().any(|&x| {
+            let x = x as i128;
+            x == steps
+                || (x >= self.prefix as i128 && steps > x && (steps - x) % self.cycle as i128 == 0)
+        })
+    }
+    /// Whether the steps on Z are exactly the multiples of the first one, which is what
+    /// taking the LCM of the first hits relies on.
+    fn lcm_compatible(&self) -> bool {
+        let Some(&first) = self.hits.first() else {
+            return false;
+        };
+        first > 0
+            && self.cycle.is_multiple_of(first)
+            && (1..=first.max(self.prefix) + self.cycle)
+                .all(|x| self.hits_at(x as i128) == x.is_multiple_of(first))
+    }
+}
+impl Display for Ghost<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: cycle entered after {} steps, {} steps long ({} instruction repeats), Z at",
+            self.start,
+            self.prefix,
+            self.cycle,
+            self.cycle / self.instructions
+        )?;
+        for &hit in self.hits.iter() {
+            match hit.checked_sub(self.prefix) {
+                Some(offset) => write!(f, " {} (+{})", hit, offset)?,
+                None => write!(f, " {} (before)", hit)?,
+            }
+        }
+        if self.hits.is_empty() {
+            write!(f, " none")?;
+        }
+        let lcm = if self.lcm_compatible() {
+            "works"
+        } else {
+            "fails"
+        };
+        write!(f, ", LCM {}", lcm)
+    }
+}
+
+/// The numbers `start + k * step` for every k >= 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Progression {
+    start: i128,
+    step: i128,
+}
+
+/// Returns gcd(a, b) and x, y with a * x + b * y = gcd(a, b).
+fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    if b == 0 {
+        (a, 1, 0)
+    } else {
+        let (g, x, y) = extended_gcd(b, a % b);
+        (g, y, x - a / b * y)
+    }
+}
+
+/// Generalised CRT for moduli that need not be coprime, `None` when the progressions
+/// never meet.
+fn intersect(a: Progression, b: Progression) -> Option<Progression> {
+    let (g, x, _) = extended_gcd(a.step, b.step);
+    let difference = b.start - a.start;
+    if difference % g != 0 {
+        return None;
+    }
+    let step = a.step / g * b.step;
+    let k = (difference / g % (b.step / g) * x).rem_euclid(b.step / g);
+    let mut start = (a.start + a.step * k).rem_euclid(step);
+    let lowest = a.start.max(b.start);
+    if start < lowest {
+        start += (lowest - start + step - 1) / step * step;
+    }
+    Some(Progression { start, step })
+}
+
+/// First step on which every ghost stands on a Z node. One-off hits before the cycles are
+/// checked directly. Once every ghost cycles, the common hits are residues modulo the lcm
+/// of the cycles, so combinations meeting in the same residue are merged.
+fn all_on_z(ghosts: &[Ghost]) -> Option<i128> {
+    let one_off = ghosts
+        .iter()
+        .flat_map(|ghost| ghost.hits.iter().filter(|&&x| x < ghost.prefix))
+        .map(|&x| x as i128)
+        .filter(|&x| ghosts.iter().all(|ghost| ghost.hits_at(x)))
+        .min();
+    let lowest = ghosts.iter().map(|x| x.prefix as i128).max().unwrap_or(0);
+    let mut step = 1;
+    let mut residues = BTreeSet::from([0]);
+    for ghost in ghosts {
+        let mut next = BTreeSet::new();
+        for &start in residues.iter() {
+            for hit in ghost.cyclic_hits() {
+                if let Some(x) = intersect(Progression { start, step }, hit) {
+                    next.insert(x.start.rem_euclid(x.step));
+                }
+            }
+        }
+        step = step / extended_gcd(step, ghost.cycle as i128).0 * ghost.cycle as i128;
+        residues = next;
+    }
+    // the first step of at least `lowest` in each residue class
+    let cyclic = residues
+        .into_iter()
+        .map(|x| x + ((lowest - x).max(0) + step - 1) / step * step)
+        .min();
+    one_off.into_iter().chain(cyclic).min()
+}
+
+fn ghosts<'a>(
+    lr_directions: &[char],
+    node_map: &HashMap<&'a str, (&'a str, &'a str)>,
+) -> Vec<Ghost<'a>> {
+    let mut starts = node_map
+        .keys()
+        .filter(|node| node.ends_with('A'))
+        .copied()
+        .collect::<Vec<_>>();
+    starts.sort();
+    starts
+        .into_iter()
+        .map(|x| Ghost::new(x, lr_directions, node_map))
+        .collect()
+}
+
+fn report(input: &str) -> String {
+    let (lr_directions, node_map) = parse_data(input);
+    let ghosts = ghosts(&lr_directions, &node_map);
+    let mut result = ghosts
+        .iter()
+        .map(|x| format!("{}\n", x))
+        .collect::<String>();
+    if ghosts.iter().all(|x| x.lcm_compatible()) {
+        result += "LCM shortcut applies\n";
+    } else {
+        result += "LCM shortcut does not apply\n";
+    }
+    result
+}
+
+#[timed]
+fn part2(input: &str) -> u128 {
+    let (lr_directions, node_map) = parse_data(input);
+    all_on_z(&ghosts(&lr_directions, &node_map)).unwrap() as u128
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -140,6 +354,11 @@ mod tests {
         assert_eq!(result, expected);
     }
     #[test]
+    fn line_endings_test() {
+        let input = get_test_input(InputType::Test).replace("\r\n", "\n");
+        assert_eq!(parse_data(&input), parse_data(&input.replace('\n', "\r\n")));
+    }
+    #[test]
     fn part1_test2() {
         let expected = 6;
         let result = part1(&get_test_input(InputType::Other("test2")));
@@ -157,4 +376,81 @@ mod tests {
         let result = part2_bruteforce(&get_test_input(InputType::Other("test3 part2")));
         assert_eq!(result, expected);
     }
+    #[test]
+    fn part2_test() {
+        let expected = 6;
+        let result = part2(&get_test_input(InputType::Other("test3 part2")));
+        assert_eq!(result, expected);
+        let input = get_test_input(InputType::Other("test4 part2 wont work for lcm"));
+        assert_eq!(part2(&input), part2_bruteforce(&input));
+        assert_ne!(part2(&input), part2_lcm(&input));
+    }
+
+    #[test]
+    fn intersect_test() {
+        let a = Progression { start: 2, step: 4 };
+        let b = Progression { start: 5, step: 6 };
+        assert_eq!(intersect(a, b), None);
+        let b = Progression { start: 12, step: 6 };
+        assert_eq!(
+            intersect(a, b),
+            Some(Progression {
+                start: 18,
+                step: 12
+            })
+        );
+        let b = Progression { start: 3, step: 5 };
+        assert_eq!(
+            intersect(a, b),
+            Some(Progression {
+                start: 18,
+                step: 20
+            })
+        );
+    }
+
+    #[test]
+    fn report_test() {
+        let expected = "11A: cycle entered after 1 steps, 2 steps long (1 instruction repeats), Z at 2 (+1), LCM works
+22A: cycle entered after 1 steps, 6 steps long (3 instruction repeats), Z at 3 (+2) 6 (+5), LCM works
+LCM shortcut applies
+";
+        assert_eq!(
+            report(&get_test_input(InputType::Other("test3 part2"))),
+            expected
+        );
+        let report = report(&get_test_input(InputType::Other(
+            "test4 part2 wont work for lcm",
+        )));
+        assert!(report.ends_with("LCM fails\nLCM shortcut does not apply\n"));
+    }
+
+    #[test]
+    fn multiple_hits_test() {
+        let input = "L
+
+11A = (11B, 11B)
+11B = (11Z, 11Z)
+11Z = (11C, 11C)
+11C = (12Z, 12Z)
+12Z = (11D, 11D)
+11D = (11B, 11B)
+22A = (22B, 22B)
+22B = (21Z, 21Z)
+21Z = (22Z, 22Z)
+22Z = (22C, 22C)
+22C = (22D, 22D)
+22D = (23Z, 23Z)
+23Z = (22B, 22B)
+33A = (31Z, 31Z)
+31Z = (33B, 33B)
+33B = (32Z, 32Z)
+32Z = (33C, 33C)
+33C = (31Z, 31Z)";
+        let (lr_directions, node_map) = parse_data(input);
+        let ghosts = ghosts(&lr_directions, &node_map);
+        let hits = ghosts.iter().map(|x| x.hits.len()).collect::<Vec<_>>();
+        assert_eq!(hits, vec![2, 3, 2]);
+        assert_eq!(part2(input), part2_bruteforce(input));
+    }
 }
@@ -1,3 +1,5 @@
+use std::fmt::{self, Display, Formatter};
+
 use aoc2023::{read_input, InputType};
 use timed::timed;
 
@@ -6,44 +8,129 @@ const DAY: u8 = 9;
 fn main() {
     let input = read_input(DAY, InputType::Real).unwrap();
 
-    println!("Part1: {}", part1(&input));
-    println!("Part2: {}", part2(&input));
+    match part1(&input) {
+        Ok(result) => println!("Part1: {}", result),
+        Err(error) => println!("Part1 failed: {}", error),
+    }
+    match part2(&input) {
+        Ok(result) => println!("Part2: {}", result),
+        Err(error) => println!("Part2 failed: {}", error),
+    }
+    if std::env::args().any(|arg| arg == "--degrees") {
+        for (index, polynomial) in parse(&input).unwrap_or_default().iter().enumerate() {
+            match polynomial.degree() {
+                Some(degree) => println!("{}: degree {}", index, degree),
+                None => println!("{}: zero", index),
+            }
+        }
+    }
 }
 
-fn generate_sequence_output(mut sequence: Vec<i32>) -> i32 {
-    let mut result = 0;
-    while !sequence.iter().all(|&x| x == 0) {
-        result += sequence.last().unwrap();
-        let mut next_sequence = vec![];
-        for pair in sequence.windows(2) {
-            next_sequence.push(pair[1] - pair[0]);
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ExtrapolationError {
+    /// The differences of the whole sequence never become all zero.
+    NotPolynomial(usize),
+    Overflow,
+}
+impl Display for ExtrapolationError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            ExtrapolationError::NotPolynomial(length) => {
+                write!(f, "differences of {} values never reach zero", length)
+            }
+            ExtrapolationError::Overflow => write!(f, "value does not fit in i128"),
         }
-        sequence = next_sequence;
     }
-    result
 }
 
-#[timed]
-fn part1(input: &str) -> i32 {
+/// Polynomial through the values at 0, 1, ..., kept in Newton form as the first value of
+/// every row of differences.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Polynomial {
+    length: usize,
+    differences: Vec<i128>,
+}
+impl Polynomial {
+    fn fit(mut sequence: Vec<i128>) -> Result<Polynomial, ExtrapolationError> {
+        let length = sequence.len();
+        let mut differences = vec![];
+        while sequence.iter().any(|&x| x != 0) {
+            if sequence.len() == 1 {
+                return Err(ExtrapolationError::NotPolynomial(length));
+            }
+            differences.push(sequence[0]);
+            sequence = sequence
+                .windows(2)
+                .map(|pair| pair[1].checked_sub(pair[0]))
+                .collect::<Option<_>>()
+                .ok_or(ExtrapolationError::Overflow)?;
+        }
+        Ok(Polynomial {
+            length,
+            differences,
+        })
+    }
+
+    /// Degree of the polynomial, `None` when it is zero everywhere.
+    fn degree(&self) -> Option<usize> {
+        self.differences.len().checked_sub(1)
+    }
+
+    /// Newton's forward formula, the sum of C(x, k) times the k-th difference.
+    fn value(&self, x: i128) -> Result<i128, ExtrapolationError> {
+        let mut binomial = 1i128;
+        let mut result = 0i128;
+        for (k, &difference) in self.differences.iter().enumerate() {
+            if k > 0 {
+                // the product of k consecutive integers is divisible by k!
+                binomial = binomial
+                    .checked_mul(x - (k as i128 - 1))
+                    .ok_or(ExtrapolationError::Overflow)?
+                    / k as i128;
+            }
+            result = binomial
+                .checked_mul(difference)
+                .and_then(|x| result.checked_add(x))
+                .ok_or(ExtrapolationError::Overflow)?;
+        }
+        Ok(result)
+    }
+
+    /// Value `steps` after the last one of the sequence.
+    fn forward(&self, steps: i128) -> Result<i128, ExtrapolationError> {
+        self.value(self.length as i128 - 1 + steps)
+    }
+
+    /// Value `steps` before the first one of the sequence.
+    fn backward(&self, steps: i128) -> Result<i128, ExtrapolationError> {
+        self.value(-steps)
+    }
+}
+
+fn parse(input: &str) -> Result<Vec<Polynomial>, ExtrapolationError> {
     input
         .lines()
         .map(|x| x.split(' ').flat_map(|x| x.parse()).collect::<Vec<_>>())
-        .map(generate_sequence_output)
-        .sum()
+        .map(Polynomial::fit)
+        .collect()
+}
+
+fn sum(
+    mut values: impl Iterator<Item = Result<i128, ExtrapolationError>>,
+) -> Result<i128, ExtrapolationError> {
+    values.try_fold(0i128, |acc, x| {
+        acc.checked_add(x?).ok_or(ExtrapolationError::Overflow)
+    })
 }
 
 #[timed]
-fn part2(input: &str) -> i32 {
-    input
-        .lines()
-        .map(|x| {
-            x.split(' ')
-                .flat_map(|x| x.parse())
-                .rev()
-                .collect::<Vec<_>>()
-        })
-        .map(generate_sequence_output)
-        .sum()
+fn part1(input: &str) -> Result<i128, ExtrapolationError> {
+    sum(parse(input)?.iter().map(|x| x.forward(1)))
+}
+
+#[timed]
+fn part2(input: &str) -> Result<i128, ExtrapolationError> {
+    sum(parse(input)?.iter().map(|x| x.backward(1)))
 }
 
 #[cfg(test)]
@@ -56,14 +143,50 @@ mod tests {
 
     #[test]
     fn part1_test() {
-        let expected = 114;
+        let expected = Ok(114);
         let result = part1(&get_test_input(InputType::Test));
         assert_eq!(result, expected);
     }
     #[test]
     fn part2_test() {
-        let expected = 2;
+        let expected = Ok(2);
         let result = part2(&get_test_input(InputType::Test));
         assert_eq!(result, expected);
     }
+
+    #[test]
+    fn polynomial_test() {
+        let polynomials = parse(&get_test_input(InputType::Test)).unwrap();
+        let degrees = polynomials.iter().map(|x| x.degree()).collect::<Vec<_>>();
+        assert_eq!(degrees, vec![Some(1), Some(2), Some(3)]);
+        let squares = Polynomial::fit(vec![1, 3, 6, 10, 15, 21]).unwrap();
+        let expected = [28, 36, 45, 55];
+        for (steps, value) in (1..).zip(expected) {
+            assert_eq!(squares.forward(steps), Ok(value));
+        }
+        assert_eq!(squares.backward(1), Ok(0));
+        assert_eq!(squares.backward(3), Ok(1));
+        assert_eq!(polynomials[2].backward(1), Ok(5));
+        assert_eq!(
+            polynomials[2].forward(1_000_000),
+            Ok(333_337_333_352_000_045)
+        );
+        assert_eq!(Polynomial::fit(vec![0, 0]).unwrap().degree(), None);
+    }
+
+    #[test]
+    fn error_test() {
+        let error = Polynomial::fit(vec![1, 2, 4]).unwrap_err();
+        assert_eq!(error, ExtrapolationError::NotPolynomial(3));
+        assert_eq!(
+            error.to_string(),
+            "differences of 3 values never reach zero"
+        );
+        let cubes = Polynomial::fit(vec![0, 1, 8, 27, 64]).unwrap();
+        assert_eq!(
+            cubes.forward(1_000_000_000_000_000),
+            Err(ExtrapolationError::Overflow)
+        );
+        assert_eq!(cubes.backward(1_000_000), Ok(-1_000_000_000_000_000_000));
+    }
 }
@@ -1,10 +1,24 @@
-use aoc2023::{read_input, InputType};
+use aoc2023::{polygon::Polygon, read_input, InputType};
 use timed::timed;
 
 const DAY: u8 = 10;
 
 fn main() {
     let input = read_input(DAY, InputType::Real).unwrap();
+    if std::env::args().any(|arg| arg == "--all-loops") {
+        let map = parse(&input);
+        for positions in find_loops(&map) {
+            let start = &positions[0];
+            println!(
+                "Loop at {},{}: length {}, enclosed {}",
+                start.x - 1,
+                start.y - 1,
+                positions.len(),
+                enclosed_flood(&map, &positions)
+            );
+        }
+        return;
+    }
 
     println!("Part1: {}", part1(&input));
     println!("Part2: {}", part2(&input));
@@ -28,82 +42,12 @@ fn parse(input: &str) -> Vec<Vec<char>> {
         .collect()
 }
 
-fn find_available_directions(
-    map: &Vec<Vec<char>>,
-    position: &Position,
-) -> (char, Direction, Direction) {
-    let if_up = match map[position.y - 1][position.x] {
-        '|' => true,
-        'F' => true,
-        '7' => true,
-        _ => false,
-    };
-    let if_right = match map[position.y][position.x + 1] {
-        '-' => true,
-        '7' => true,
-        'J' => true,
-        _ => false,
-    };
-    let if_down = match map[position.y + 1][position.x] {
-        '|' => true,
-        'L' => true,
-        'J' => true,
-        _ => false,
-    };
-    let if_left = match map[position.y][position.x - 1] {
-        '-' => true,
-        'F' => true,
-        'L' => true,
-        _ => false,
-    };
-
-    let character: char;
-    let first: Direction;
-    let second: Direction;
-    if if_up {
-        first = Direction::Up;
-        if if_right {
-            character = 'L';
-            second = Direction::Right;
-        } else if if_down {
-            character = '|';
-            second = Direction::Down;
-        } else if if_left {
-            character = 'J';
-            second = Direction::Left;
-        } else {
-            unreachable!()
-        }
-    } else if if_right {
-        first = Direction::Right;
-        if if_down {
-            character = 'F';
-            second = Direction::Down;
-        } else if if_left {
-            character = '-';
-            second = Direction::Left;
-        } else {
-            unreachable!()
-        }
-    } else if if_down {
-        character = '7';
-        first = Direction::Down;
-        second = Direction::Left;
-    } else {
-        unreachable!()
-    }
-    (character, first, second)
-}
-
-fn find_starting(map: &Vec<Vec<char>>) -> Position {
-    for (y, line) in map.iter().enumerate() {
-        for (x, &pipe) in line.iter().enumerate() {
-            if pipe == 'S' {
-                return Position::new(x, y);
-            }
-        }
-    }
-    unreachable!()
+fn find_starting(map: &[Vec<char>]) -> Option<Position> {
+    map.iter().enumerate().find_map(|(y, line)| {
+        line.iter()
+            .position(|&pipe| pipe == 'S')
+            .map(|x| Position::new(x, y))
+    })
 }
 #[derive(Debug, Clone, PartialEq)]
 struct Position {
@@ -115,7 +59,7 @@ impl Position {
         Position { x, y }
     }
 }
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 enum Direction {
     Up,
     Right,
@@ -131,143 +75,167 @@ impl Direction {
             Direction::Left => Position::new(positon.x - 1, positon.y),
         }
     }
-    fn get_next(&self, map: &Vec<Vec<char>>, positon: &Position) -> Direction {
-        let next_pipe_position = self.apply_direction(positon);
-
-        match map[next_pipe_position.y][next_pipe_position.x] {
-            'L' => match self {
-                Direction::Down => Direction::Right,
-                Direction::Left => Direction::Up,
-                _ => unreachable!(),
-            },
-            'J' => match self {
-                Direction::Down => Direction::Left,
-                Direction::Right => Direction::Up,
-                _ => unreachable!(),
-            },
-            '7' => match self {
-                Direction::Right => Direction::Down,
-                Direction::Up => Direction::Left,
-                _ => unreachable!(),
-            },
-            'F' => match self {
-                Direction::Left => Direction::Down,
-                Direction::Up => Direction::Right,
-                _ => unreachable!(),
-            },
-            _ => self.clone(),
+    fn opposite(&self) -> Direction {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Right => Direction::Left,
+            Direction::Down => Direction::Up,
+            Direction::Left => Direction::Right,
         }
     }
 }
 
-#[timed]
-fn part1(input: &str) -> u32 {
-    let map = parse(input);
-    let starting = find_starting(&map);
-    let mut result = 0;
-    let (_, mut head1_dir, mut head2_dir) = find_available_directions(&map, &starting);
+const PIPES: [(char, [Direction; 2]); 6] = [
+    ('|', [Direction::Up, Direction::Down]),
+    ('-', [Direction::Right, Direction::Left]),
+    ('L', [Direction::Up, Direction::Right]),
+    ('J', [Direction::Up, Direction::Left]),
+    ('7', [Direction::Down, Direction::Left]),
+    ('F', [Direction::Right, Direction::Down]),
+];
 
-    let mut head1_pos = starting.clone();
-    let mut head2_pos = starting.clone();
-
-    while head1_pos != head2_pos || result == 0 {
-        let tmp1 = head1_dir.apply_direction(&head1_pos);
-        let tmp2 = head2_dir.apply_direction(&head2_pos);
-        head1_dir = head1_dir.get_next(&map, &head1_pos);
-        head2_dir = head2_dir.get_next(&map, &head2_pos);
-        head1_pos = tmp1;
-        head2_pos = tmp2;
+fn connections(pipe: char) -> Option<[Direction; 2]> {
+    PIPES.iter().find(|x| x.0 == pipe).map(|x| x.1)
+}
 
-        result += 1;
+/// Follows the pipes from `start` leaving in `direction`, returns the visited positions
+/// and the direction the walk came back in, or `None` if it never returns.
+fn follow(
+    map: &[Vec<char>],
+    start: &Position,
+    mut direction: Direction,
+) -> Option<(Vec<Position>, Direction)> {
+    let mut positions = vec![start.clone()];
+    let mut current = start.clone();
+    for _ in 0..map.len() * map[0].len() {
+        current = direction.apply_direction(&current);
+        if &current == start {
+            return Some((positions, direction));
+        }
+        let [first, second] = connections(map[current.y][current.x])?;
+        direction = match direction.opposite() {
+            x if x == first => second,
+            x if x == second => first,
+            _ => return None,
+        };
+        positions.push(current.clone());
     }
-
-    result
+    None
 }
 
-fn check_if_inside(map: &Vec<Vec<char>>, positon: Position) -> bool {
-    // Left
-    let mut count = 0;
-    let str = &map[positon.y][..positon.x]
-        .iter()
-        .collect::<String>()
-        .replace('-', "");
-    count += str.match_indices("|").count();
-    count += str.match_indices("FJ").count();
-    count += str.match_indices("L7").count();
-    if count % 2 == 0 {
-        return false;
-    }
-    // Right
-    let mut count = 0;
-    let str = &map[positon.y][positon.x + 1..]
-        .iter()
-        .collect::<String>()
-        .replace('-', "");
-    count += str.match_indices("|").count();
-    count += str.match_indices("FJ").count();
-    count += str.match_indices("L7").count();
-    if count % 2 == 0 {
-        return false;
-    }
-    // Up
-    let mut count = 0;
-    let str = &map.iter().map(|line| line[positon.x]).collect::<Vec<_>>()[..positon.y]
-        .iter()
-        .collect::<String>()
-        .replace('|', "");
-    count += str.match_indices("-").count();
-    count += str.match_indices("FJ").count();
-    count += str.match_indices("7L").count();
-    if count % 2 == 0 {
-        return false;
-    }
-    // Down
-    let mut count = 0;
-    let str = &map.iter().map(|line| line[positon.x]).collect::<Vec<_>>()[positon.y + 1..]
-        .iter()
-        .collect::<String>()
-        .replace('|', "");
-    count += str.match_indices("-").count();
-    count += str.match_indices("FJ").count();
-    count += str.match_indices("7L").count();
-    if count % 2 == 0 {
-        return false;
-    }
-    return true;
+/// Finds the loop through `S`, as the tile hidden under it and the loop positions in
+/// order. Every pair of neighbours connecting to `S` is tried until one closes a loop.
+fn find_loop(map: &[Vec<char>]) -> Option<(char, Vec<Position>)> {
+    let start = find_starting(map)?;
+    let candidates = [
+        Direction::Up,
+        Direction::Right,
+        Direction::Down,
+        Direction::Left,
+    ]
+    .into_iter()
+    .filter(|direction| {
+        let next = direction.apply_direction(&start);
+        connections(map[next.y][next.x]).is_some_and(|x| x.contains(&direction.opposite()))
+    })
+    .collect::<Vec<_>>();
+    candidates.iter().find_map(|&direction| {
+        let (positions, back) = follow(map, &start, direction)?;
+        let pipe = PIPES.iter().find(|x| {
+            x.1.contains(&direction)
+                && x.1.contains(&back.opposite())
+                && direction != back.opposite()
+        })?;
+        Some((pipe.0, positions))
+    })
 }
+
 #[timed]
-fn part2(input: &str) -> u32 {
+fn part1(input: &str) -> u32 {
     let map = parse(input);
-    let starting = find_starting(&map);
-    let (start_char, mut head_dir, _) = find_available_directions(&map, &starting);
+    let (_, positions) = find_loop(&map).unwrap();
+    positions.len() as u32 / 2
+}
 
-    let mut head_pos = starting.clone();
-    let mut new_map = vec![vec!['.'; map[0].len()]; map.len()];
+fn enclosed_pick(positions: &[Position]) -> u32 {
+    let points = positions
+        .iter()
+        .chain(positions.first())
+        .map(|x| (x.x as i64, x.y as i64))
+        .collect::<Vec<_>>();
+    Polygon::new(&points).unwrap().interior_points() as u32
+}
 
-    new_map[starting.y][starting.x] = start_char;
-    loop {
-        let tmp = head_dir.apply_direction(&head_pos);
-        if tmp == starting {
-            break;
+/// Counts the tiles inside the loop by flood filling the outside of a grid at double
+/// resolution, where the gaps between tiles are cells too and pipes can be squeezed past.
+fn enclosed_flood(map: &[Vec<char>], positions: &[Position]) -> u32 {
+    let (width, height) = (map[0].len() * 2, map.len() * 2);
+    let mut blocked = vec![vec![false; width]; height];
+    for (current, next) in positions.iter().zip(positions.iter().cycle().skip(1)) {
+        blocked[current.y * 2][current.x * 2] = true;
+        blocked[current.y + next.y][current.x + next.x] = true;
+    }
+    let mut outside = vec![vec![false; width]; height];
+    let mut stack = vec![(0usize, 0usize)];
+    outside[0][0] = true;
+    while let Some((x, y)) = stack.pop() {
+        let neighbours = [
+            (x.wrapping_sub(1), y),
+            (x + 1, y),
+            (x, y.wrapping_sub(1)),
+            (x, y + 1),
+        ];
+        for (x, y) in neighbours {
+            if x < width && y < height && !blocked[y][x] && !outside[y][x] {
+                outside[y][x] = true;
+                stack.push((x, y));
+            }
         }
-        head_dir = head_dir.get_next(&map, &head_pos);
-        head_pos = tmp;
-
-        new_map[head_pos.y][head_pos.x] = map[head_pos.y][head_pos.x];
     }
+    (0..map.len())
+        .flat_map(|y| (0..map[0].len()).map(move |x| (x, y)))
+        .filter(|&(x, y)| !blocked[y * 2][x * 2] && !outside[y * 2][x * 2])
+        .count() as u32
+}
 
-    let mut result = 0;
-    for (y, line) in new_map.iter().enumerate() {
-        for (x, character) in line.iter().enumerate() {
-            if character == &'.' && check_if_inside(&new_map, Position::new(x, y)) {
-                result += 1
+/// Finds every closed loop of pipes, with `S` standing for the pipe it hides.
+fn find_loops(map: &[Vec<char>]) -> Vec<Vec<Position>> {
+    let mut map = map.to_vec();
+    if let (Some(start), Some((pipe, _))) = (find_starting(&map), find_loop(&map)) {
+        map[start.y][start.x] = pipe;
+    }
+    let mut checked = vec![vec![false; map[0].len()]; map.len()];
+    let mut result = vec![];
+    for y in 0..map.len() {
+        for x in 0..map[0].len() {
+            let Some([first, second]) = connections(map[y][x]) else {
+                continue;
+            };
+            if checked[y][x] {
+                continue;
+            }
+            let start = Position::new(x, y);
+            match follow(&map, &start, first) {
+                Some((positions, back)) if back == second.opposite() => {
+                    positions.iter().for_each(|x| checked[x.y][x.x] = true);
+                    result.push(positions);
+                }
+                // a tile on a dead end can't be on any loop either
+                Some((positions, _)) => positions.iter().for_each(|x| checked[x.y][x.x] = true),
+                None => checked[y][x] = true,
             }
         }
     }
-
     result
 }
 
+#[timed]
+fn part2(input: &str) -> u32 {
+    let map = parse(input);
+    let (_, positions) = find_loop(&map).unwrap();
+    enclosed_pick(&positions)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -378,4 +346,74 @@ mod tests {
         let result = part2(&get_test_input(InputType::Other("reddit")));
         assert_eq!(result, expected);
     }
+
+    #[test]
+    fn loop_test() {
+        let map = parse(&get_test_input(InputType::Test));
+        let (start_pipe, positions) = find_loop(&map).unwrap();
+        assert_eq!(start_pipe, 'F');
+        let positions = positions
+            .iter()
+            .map(|x| (x.x - 1, x.y - 1))
+            .collect::<Vec<_>>();
+        let expected = vec![
+            (1, 1),
+            (2, 1),
+            (3, 1),
+            (3, 2),
+            (3, 3),
+            (2, 3),
+            (1, 3),
+            (1, 2),
+        ];
+        assert_eq!(positions, expected);
+    }
+
+    #[test]
+    fn start_inference_test() {
+        let cases = [
+            ("F7\nLS", Some('J')),
+            ("-S-\n...", None),
+            (".F7\n.|S\n.LJ", Some('|')),
+            ("F-7.\n|.S7\nL-JL", Some('|')),
+            ("F7F7\n|LS|\nL--J", Some('J')),
+        ];
+        for (input, expected) in cases {
+            let result = find_loop(&parse(input)).map(|x| x.0);
+            assert_eq!(result, expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn all_loops_test() {
+        let map = parse(&get_test_input(InputType::Other("test-p2")));
+        let loops = find_loops(&map);
+        assert_eq!(loops.len(), 1);
+        assert_eq!(enclosed_flood(&map, &loops[0]), 4);
+
+        let input = "F-----7F7\n|F---7|LJ\n||F7.||..\n||LJ.||F7\n|L---J|||\nL-----JLJ";
+        let map = parse(input);
+        let result = find_loops(&map)
+            .iter()
+            .map(|x| (x.len(), enclosed_flood(&map, x)))
+            .collect::<Vec<_>>();
+        assert_eq!(result, vec![(22, 20), (4, 0), (14, 6), (4, 0), (6, 0)]);
+    }
+
+    #[test]
+    fn flood_matches_pick_test() {
+        for input_type in ["test-p2", "test2-p2", "test3-p2", "DP", "WJ", "reddit"] {
+            let map = parse(&get_test_input(InputType::Other(input_type)));
+            let (_, positions) = find_loop(&map).unwrap();
+            let expected = enclosed_pick(&positions);
+            assert_eq!(enclosed_flood(&map, &positions), expected, "{}", input_type);
+        }
+    }
+
+    #[test]
+    fn empty_loop_test() {
+        assert_eq!(part1("F7\nLS"), 2);
+        assert_eq!(part2("F7\nLS"), 0);
+        assert_eq!(part2("F--7\n|F7|\n||LJ\nLS.."), 0);
+    }
 }
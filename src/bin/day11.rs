@@ -8,6 +8,22 @@ fn main() {
 
     println!("Part1: {}", part1(&input));
     println!("Part2: {}", part2(&input));
+    if std::env::args().any(|arg| arg == "--matrix") {
+        print!("{}", distance_matrix(&expand(&parse(&input), 2).unwrap()));
+    }
+    if std::env::args().any(|arg| arg == "--nearest") {
+        let galaxies = expand(&parse(&input), 2).unwrap();
+        for (i, closest) in nearest(&galaxies, 3).into_iter().enumerate() {
+            let closest = closest
+                .into_iter()
+                .map(|(j, distance)| format!("{} ({})", j + 1, distance))
+                .collect::<Vec<_>>();
+            println!("{}: {}", i + 1, closest.join(", "));
+        }
+        if let Some((a, b, distance)) = farthest_pair(&galaxies) {
+            println!("Farthest: {} to {} ({})", a + 1, b + 1, distance);
+        }
+    }
 }
 #[derive(Debug)]
 struct Point {
@@ -18,8 +34,8 @@ impl Point {
     fn new(x: usize, y: usize) -> Point {
         Point { x, y }
     }
-    fn distance(p1: &Point, p2: &Point) -> usize {
-        p1.x.abs_diff(p2.x) + p1.y.abs_diff(p2.y)
+    fn distance(&self, other: &Point) -> usize {
+        self.x.abs_diff(other.x) + self.y.abs_diff(other.y)
     }
 }
 
@@ -27,43 +43,127 @@ fn parse(input: &str) -> Vec<Vec<char>> {
     input.lines().map(|x| x.chars().collect()).collect()
 }
 
-fn calculate_result(expand_rate: usize, input: &str) -> usize {
-    let parsed = parse(input);
-    let mut points = vec![];
-    let mut y_offset = 0;
-    for (y, line) in parsed.iter().enumerate() {
-        let mut x_offset = 0;
-        if parsed[y].iter().all(|&x| x == '.') {
-            y_offset += expand_rate;
-            continue;
-        }
-        for (x, character) in line.iter().enumerate() {
-            if parsed.iter().all(|line| line[x] == '.') {
-                x_offset += expand_rate;
-                continue;
-            }
-            if *character == '#' {
-                points.push(Point::new(x + x_offset, y + y_offset))
-            }
-        }
+/// `result[i]` is the number of empty lines before line `i`.
+fn empty_before(occupied: &[bool]) -> Vec<usize> {
+    let mut result = vec![0; occupied.len() + 1];
+    for (i, &occupied) in occupied.iter().enumerate() {
+        result[i + 1] = result[i] + !occupied as usize;
     }
-    let mut result = 0;
-    for i in 0..points.len() {
-        for j in i + 1..points.len() {
-            result += Point::distance(&points[i], &points[j])
+    result
+}
+
+/// Galaxy positions after every empty row and column is replaced by `factor` of them.
+fn expand(map: &[Vec<char>], factor: usize) -> Result<Vec<Point>, String> {
+    if factor == 0 {
+        return Err("expansion factor must be at least 1".to_string());
+    }
+    let galaxies = map
+        .iter()
+        .enumerate()
+        .flat_map(|(y, line)| {
+            line.iter()
+                .enumerate()
+                .filter(|(_, &x)| x == '#')
+                .map(move |(x, _)| Point::new(x, y))
+        })
+        .collect::<Vec<_>>();
+    let mut rows = vec![false; map.len()];
+    let mut columns = vec![false; map.first().map_or(0, |x| x.len())];
+    for galaxy in galaxies.iter() {
+        rows[galaxy.y] = true;
+        columns[galaxy.x] = true;
+    }
+    let (rows, columns) = (empty_before(&rows), empty_before(&columns));
+    let galaxies = galaxies
+        .into_iter()
+        .map(|Point { x, y }| Point::new(x + columns[x] * (factor - 1), y + rows[y] * (factor - 1)))
+        .collect();
+    Ok(galaxies)
+}
+
+/// Sum of the distances between every pair of coordinates, each coordinate is
+/// subtracted by all before it once sorted.
+fn pair_sum(mut coordinates: Vec<usize>) -> usize {
+    coordinates.sort_unstable();
+    let mut before = 0;
+    coordinates
+        .into_iter()
+        .enumerate()
+        .map(|(i, x)| {
+            let result = x * i - before;
+            before += x;
+            result
+        })
+        .sum()
+}
+
+fn calculate_result(factor: usize, input: &str) -> Result<usize, String> {
+    let galaxies = expand(&parse(input), factor)?;
+    Ok(pair_sum(galaxies.iter().map(|x| x.x).collect())
+        + pair_sum(galaxies.iter().map(|x| x.y).collect()))
+}
+
+/// Distances between all galaxies, with galaxies numbered from 1 as in the puzzle.
+fn distance_matrix(galaxies: &[Point]) -> String {
+    let mut result = (1..=galaxies.len()).fold(String::new(), |acc, i| acc + "," + &i.to_string());
+    for (i, galaxy) in galaxies.iter().enumerate() {
+        result += &format!("\n{}", i + 1);
+        for other in galaxies {
+            result += &format!(",{}", galaxy.distance(other));
         }
     }
-    result
+    result + "\n"
+}
+
+/// The `k` closest other galaxies of each galaxy as (index, distance), closest first.
+fn nearest(galaxies: &[Point], k: usize) -> Vec<Vec<(usize, usize)>> {
+    galaxies
+        .iter()
+        .enumerate()
+        .map(|(i, galaxy)| {
+            let mut others = galaxies
+                .iter()
+                .enumerate()
+                .filter(|&(j, _)| j != i)
+                .map(|(j, other)| (j, galaxy.distance(other)))
+                .collect::<Vec<_>>();
+            others.sort_by_key(|&(j, distance)| (distance, j));
+            others.truncate(k);
+            others
+        })
+        .collect()
+}
+
+/// Indices and distance of the farthest galaxies. A manhattan distance is the largest
+/// difference of either x + y or x - y, so only their extremes need comparing.
+fn farthest_pair(galaxies: &[Point]) -> Option<(usize, usize, usize)> {
+    let sum = |i: &usize| galaxies[*i].x + galaxies[*i].y;
+    let difference = |i: &usize| galaxies[*i].x as i64 - galaxies[*i].y as i64;
+    let indices = 0..galaxies.len();
+    let candidates = [
+        (
+            indices.clone().min_by_key(sum)?,
+            indices.clone().max_by_key(sum)?,
+        ),
+        (
+            indices.clone().min_by_key(difference)?,
+            indices.max_by_key(difference)?,
+        ),
+    ];
+    candidates
+        .into_iter()
+        .map(|(a, b)| (a.min(b), a.max(b), galaxies[a].distance(&galaxies[b])))
+        .max_by_key(|&(_, _, distance)| distance)
 }
 
 #[timed]
 fn part1(input: &str) -> usize {
-    calculate_result(1, input)
+    calculate_result(2, input).unwrap()
 }
 
 #[timed]
 fn part2(input: &str) -> usize {
-    calculate_result(1_000_000 - 1, input)
+    calculate_result(1_000_000, input).unwrap()
 }
 
 #[cfg(test)]
@@ -86,4 +186,47 @@ mod tests {
         let result = part2(&get_test_input(InputType::Test));
         assert_eq!(result, expected);
     }
+
+    #[test]
+    fn factor_test() {
+        let input = get_test_input(InputType::Test);
+        assert_eq!(calculate_result(10, &input), Ok(1030));
+        assert_eq!(calculate_result(100, &input), Ok(8410));
+        assert_eq!(calculate_result(1, &input), Ok(292));
+        assert_eq!(
+            calculate_result(0, &input),
+            Err("expansion factor must be at least 1".to_string())
+        );
+    }
+
+    #[test]
+    fn query_test() {
+        let galaxies = expand(&parse(&get_test_input(InputType::Test)), 2).unwrap();
+        assert_eq!(galaxies.len(), 9);
+        assert_eq!((galaxies[0].x, galaxies[0].y), (4, 0));
+        assert_eq!(galaxies[4].distance(&galaxies[8]), 9);
+        assert_eq!(galaxies[0].distance(&galaxies[6]), 15);
+        assert_eq!(galaxies[2].distance(&galaxies[5]), 17);
+        assert_eq!(galaxies[7].distance(&galaxies[8]), 5);
+
+        let matrix = distance_matrix(&galaxies);
+        let lines = matrix.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], ",1,2,3,4,5,6,7,8,9");
+        assert_eq!(lines[5].split(',').nth(9), Some("9"));
+        let total = lines[1..]
+            .iter()
+            .flat_map(|x| x.split(',').skip(1))
+            .map(|x| x.parse::<usize>().unwrap())
+            .sum::<usize>();
+        assert_eq!(total, 374 * 2);
+
+        let closest = nearest(&galaxies, 2);
+        assert_eq!(closest[7], vec![(8, 5), (4, 6)]);
+        let brute_force = (0..9)
+            .flat_map(|a| (a + 1..9).map(move |b| (a, b)))
+            .map(|(a, b)| galaxies[a].distance(&galaxies[b]))
+            .max();
+        assert_eq!(farthest_pair(&galaxies).map(|x| x.2), brute_force);
+        assert_eq!(farthest_pair(&[]), None);
+    }
 }
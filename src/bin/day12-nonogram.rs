@@ -0,0 +1,42 @@
+use aoc2023::{
+    nonogram::{parse, render_grid, solve, Solution},
+    read_input, InputType,
+};
+use timed::timed;
+
+const DAY: u8 = 12;
+
+fn main() {
+    let input = read_input(DAY, InputType::Other("nonogram")).unwrap();
+
+    print!("{}", solution(&input));
+}
+
+#[timed]
+fn solution(input: &str) -> String {
+    match solve(&parse(input).unwrap()) {
+        Solution::None => "No solution\n".to_string(),
+        Solution::Unique(grid) => render_grid(&grid),
+        Solution::Multiple(first, second) => format!(
+            "Multiple solutions, for example\n{}\n{}",
+            render_grid(&first),
+            render_grid(&second)
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_test_input(input_type: InputType) -> String {
+        read_input(DAY, input_type).unwrap()
+    }
+
+    #[test]
+    fn solution_test() {
+        let expected = "..####....\n.#....#...\n#.#..#.#..\n#......#..\n#.#..#.#.#\n#..##..#.#\n.#....#.##\n..####..#.\n...##..#..\n..####.#..\n";
+        let result = solution(&get_test_input(InputType::Other("nonogram")));
+        assert_eq!(result, expected);
+    }
+}
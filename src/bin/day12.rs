@@ -1,6 +1,7 @@
-use std::collections::HashMap;
-
-use aoc2023::{read_input, InputType};
+use aoc2023::{
+    day12::{parse, render, total_arrangements},
+    read_input, InputType,
+};
 use timed::timed;
 
 const DAY: u8 = 12;
@@ -10,90 +11,25 @@ fn main() {
 
     println!("Part1: {}", part1(&input));
     println!("Part2: {}", part2(&input));
-}
-
-fn get_possible_solutions(
-    symbols: &Vec<char>,
-    numbers: &Vec<usize>,
-    mem: &mut HashMap<(usize, usize, usize), usize>,
-    current_symbol: usize,
-    current_number: usize,
-    count: usize,
-) -> usize {
-    let key = (current_symbol, current_number, count);
-    if mem.contains_key(&key) {
-        return *mem.get(&key).unwrap();
-    }
-    if current_symbol == symbols.len() {
-        return (current_number == numbers.len()
-            || (current_number == numbers.len() - 1 && count == numbers[current_number]))
-            as usize;
-    }
-    if current_number == numbers.len() {
-        return (symbols[current_symbol..].iter().all(|&x| x != '#')) as usize;
-    }
-    let mut result = 0;
-    if symbols[current_symbol] == '.' || symbols[current_symbol] == '?' {
-        if count == 0 || count == numbers[current_number] {
-            result += get_possible_solutions(
-                symbols,
-                numbers,
-                mem,
-                current_symbol + 1,
-                current_number + (count != 0) as usize,
-                0,
-            )
-        }
-    }
-    if symbols[current_symbol] == '#' || symbols[current_symbol] == '?' {
-        if count < numbers[current_number] {
-            result += get_possible_solutions(
-                symbols,
-                numbers,
-                mem,
-                current_symbol + 1,
-                current_number,
-                count + 1,
-            )
+    if std::env::args().any(|arg| arg == "--arrangements") {
+        for row in parse(&input).unwrap() {
+            let count = row.arrangements();
+            println!("{}: {}", row, count);
+            if count <= 20 {
+                row.enumerate().for_each(|x| println!("  {}", render(&x)));
+            }
         }
     }
-    mem.insert(key, result);
-    result
 }
 
 #[timed]
-fn part1(input: &str) -> usize {
-    input
-        .lines()
-        .map(|line| {
-            let line = line.split_once(' ').unwrap();
-            let symbols = line.0.chars().collect::<Vec<_>>();
-            let numbers = line
-                .1
-                .split(',')
-                .flat_map(|x| x.parse::<usize>())
-                .collect::<Vec<_>>();
-            get_possible_solutions(&symbols, &numbers, &mut HashMap::new(), 0, 0, 0)
-        })
-        .sum()
+fn part1(input: &str) -> u128 {
+    total_arrangements(&parse(input).unwrap(), 1)
 }
 
 #[timed]
-fn part2(input: &str) -> usize {
-    input
-        .lines()
-        .map(|line| {
-            let line = line.split_once(' ').unwrap();
-            let mut symbols = format!("{}?", line.0).repeat(5).chars().collect::<Vec<_>>();
-            symbols.pop();
-            let numbers = format!("{},", line.1)
-                .repeat(5)
-                .split(',')
-                .filter_map(|x| x.parse::<usize>().ok())
-                .collect::<Vec<_>>();
-            get_possible_solutions(&symbols, &numbers, &mut HashMap::new(), 0, 0, 0)
-        })
-        .sum()
+fn part2(input: &str) -> u128 {
+    total_arrangements(&parse(input).unwrap(), 5)
 }
 
 #[cfg(test)]
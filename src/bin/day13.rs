@@ -1,92 +1,188 @@
+use std::fmt::{self, Display, Formatter};
+
 use aoc2023::{read_input, InputType};
 use timed::timed;
 
 const DAY: u8 = 13;
-const NEWLINE_CHAR: &str = "\r\n";
 
 fn main() {
     let input = read_input(DAY, InputType::Real).unwrap();
 
     println!("Part1: {}", part1(&input));
     println!("Part2: {}", part2(&input));
+    if std::env::args().any(|arg| arg == "--smudges") {
+        for (index, pattern) in parse(&input).unwrap().iter().enumerate() {
+            let smudge = pattern.smudge().unwrap();
+            let (x, y) = smudge.position;
+            println!(
+                "Pattern {}: smudge at {},{}, {:?}",
+                index, x, y, smudge.axis
+            );
+            println!("{}", smudge.corrected);
+        }
+    }
 }
 
-fn parse(input: &str) -> Vec<Vec<Vec<char>>> {
-    let split_by = NEWLINE_CHAR.repeat(2);
-    input
-        .split(&split_by)
-        .map(|map| {
-            map.lines()
-                .map(|line| line.chars().collect::<Vec<_>>())
-                .collect::<Vec<_>>()
-        })
-        .collect::<Vec<_>>()
+/// Reflection line, given by the number of rows above or columns left of it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Axis {
+    Horizontal(usize),
+    Vertical(usize),
+}
+impl Axis {
+    fn summary(&self) -> usize {
+        match self {
+            Axis::Horizontal(rows) => 100 * rows,
+            Axis::Vertical(columns) => *columns,
+        }
+    }
 }
 
-fn transpose(vec: &Vec<Vec<char>>) -> Vec<Vec<char>> {
-    (0..vec[0].len())
-        .map(|i| (0..vec.len()).map(|j| vec[j][i]).collect::<Vec<_>>())
-        .collect::<Vec<_>>()
+/// Pattern with every row and column encoded as a bitmask of its rocks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Pattern {
+    rows: Vec<u64>,
+    columns: Vec<u64>,
 }
 
-fn find_reflection(map: &Vec<Vec<char>>, smudge_count: u8) -> Option<usize> {
-    for i in 1..(map.len() / 2) + 1 {
-        let up = map[..i].iter().collect::<Vec<_>>();
-        let down = map[i..i * 2].iter().rev().collect::<Vec<_>>();
-        let mut difference = 0;
-        for i in 0..up.len() {
-            for j in 0..up[0].len() {
-                if up[i][j] != down[i][j] {
-                    difference += 1;
-                }
-            }
-        }
-        if difference == smudge_count {
-            return Some(i);
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Smudge {
+    position: (usize, usize),
+    axis: Axis,
+    corrected: Pattern,
+}
+
+/// Mirrored pairs of lines around a reflection after `before` lines.
+fn pairs(count: usize, before: usize) -> impl Iterator<Item = (usize, usize)> {
+    (0..before.min(count - before)).map(move |i| (before - 1 - i, before + i))
+}
+
+fn mismatches(lines: &[u64], before: usize) -> u32 {
+    pairs(lines.len(), before)
+        .map(|(a, b)| (lines[a] ^ lines[b]).count_ones())
+        .sum()
+}
+
+impl Pattern {
+    fn new(map: &[Vec<char>]) -> Result<Pattern, String> {
+        let width = map.first().map_or(0, |x| x.len());
+        if width > 64 || map.len() > 64 {
+            return Err(format!(
+                "pattern {}x{} is over 64 cells wide",
+                width,
+                map.len()
+            ));
         }
-        let up = map[map.len() - i * 2..map.len() - i]
-            .iter()
-            .collect::<Vec<_>>();
-        let down = map[map.len() - i..].iter().rev().collect::<Vec<_>>();
-        let mut difference = 0;
-        for i in 0..up.len() {
-            for j in 0..up[0].len() {
-                if up[i][j] != down[i][j] {
-                    difference += 1;
+        let mut rows = vec![0; map.len()];
+        let mut columns = vec![0; width];
+        for (y, line) in map.iter().enumerate() {
+            if line.len() != width {
+                return Err(format!("row {} is not {} cells long", y, width));
+            }
+            for (x, &cell) in line.iter().enumerate() {
+                match cell {
+                    '#' => {
+                        rows[y] |= 1 << x;
+                        columns[x] |= 1 << y;
+                    }
+                    '.' => {}
+                    _ => return Err(format!("unknown cell `{}`", cell)),
                 }
             }
         }
-        if difference == smudge_count {
-            return Some(map.len() - i);
+        Ok(Pattern { rows, columns })
+    }
+
+    /// Every possible axis with the number of cells that don't match their mirror image.
+    fn axes(&self) -> Vec<(Axis, u32)> {
+        let horizontal =
+            (1..self.rows.len()).map(|i| (Axis::Horizontal(i), mismatches(&self.rows, i)));
+        let vertical =
+            (1..self.columns.len()).map(|i| (Axis::Vertical(i), mismatches(&self.columns, i)));
+        horizontal.chain(vertical).collect()
+    }
+
+    fn reflection(&self, mismatches: u32) -> Option<Axis> {
+        self.axes()
+            .into_iter()
+            .find(|&(_, count)| count == mismatches)
+            .map(|(axis, _)| axis)
+    }
+
+    fn flip(&mut self, (x, y): (usize, usize)) {
+        self.rows[y] ^= 1 << x;
+        self.columns[x] ^= 1 << y;
+    }
+
+    /// The smudge is the single mismatch of an axis, reported at the cell nearer the top
+    /// left. Flipping it makes that axis a perfect reflection.
+    fn smudge(&self) -> Option<Smudge> {
+        let axis = self.reflection(1)?;
+        let (lines, before) = match axis {
+            Axis::Horizontal(rows) => (&self.rows, rows),
+            Axis::Vertical(columns) => (&self.columns, columns),
+        };
+        let (line, difference) = pairs(lines.len(), before)
+            .map(|(a, b)| (a, lines[a] ^ lines[b]))
+            .find(|&(_, difference)| difference != 0)?;
+        let offset = difference.trailing_zeros() as usize;
+        let position = match axis {
+            Axis::Horizontal(_) => (offset, line),
+            Axis::Vertical(_) => (line, offset),
+        };
+        let mut corrected = self.clone();
+        corrected.flip(position);
+        Some(Smudge {
+            position,
+            axis,
+            corrected,
+        })
+    }
+}
+impl Display for Pattern {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        for row in self.rows.iter() {
+            for x in 0..self.columns.len() {
+                write!(f, "{}", if row >> x & 1 == 1 { '#' } else { '.' })?;
+            }
+            writeln!(f)?;
         }
+        Ok(())
     }
-    None
+}
+
+fn parse(input: &str) -> Result<Vec<Pattern>, String> {
+    input
+        .lines()
+        .collect::<Vec<_>>()
+        .split(|line| line.is_empty())
+        .filter(|lines| !lines.is_empty())
+        .map(|lines| {
+            let map = lines
+                .iter()
+                .map(|line| line.chars().collect::<Vec<_>>())
+                .collect::<Vec<_>>();
+            Pattern::new(&map)
+        })
+        .collect()
 }
 
 #[timed]
 fn part1(input: &str) -> usize {
-    let parsed = parse(input);
-    let mut result = 0;
-    for map in parsed {
-        match find_reflection(&map, 0) {
-            Some(row) => result += 100 * row,
-            None => result += find_reflection(&transpose(&map), 0).unwrap(),
-        }
-    }
-    result
+    parse(input)
+        .unwrap()
+        .iter()
+        .map(|pattern| pattern.reflection(0).unwrap().summary())
+        .sum()
 }
 
 #[timed]
 fn part2(input: &str) -> usize {
-    let parsed = parse(input);
-    let mut result = 0;
-    for map in parsed {
-        match find_reflection(&map, 1) {
-            Some(row) => result += 100 * row,
-            None => result += find_reflection(&transpose(&map), 1).unwrap(),
-        }
-    }
-    result
+    parse(input)
+        .unwrap()
+        .iter()
+        .map(|pattern| pattern.smudge().unwrap().axis.summary())
+        .sum()
 }
 
 #[cfg(test)]
@@ -110,4 +206,28 @@ mod tests {
         let result = part2(&get_test_input(InputType::Test));
         assert_eq!(result, expected);
     }
+
+    #[test]
+    fn axes_test() {
+        let patterns = parse(&get_test_input(InputType::Test)).unwrap();
+        let axes = patterns[0].axes();
+        assert_eq!(axes.len(), 6 + 8);
+        assert!(axes.contains(&(Axis::Vertical(5), 0)));
+        assert!(axes.contains(&(Axis::Horizontal(3), 1)));
+        assert_eq!(patterns[1].reflection(0), Some(Axis::Horizontal(4)));
+
+        let smudge = patterns[0].smudge().unwrap();
+        assert_eq!(smudge.position, (0, 0));
+        assert_eq!(smudge.axis, Axis::Horizontal(3));
+        assert_eq!(smudge.corrected.reflection(0), Some(Axis::Horizontal(3)));
+        assert!(smudge
+            .corrected
+            .to_string()
+            .starts_with("..##..##.\n..#.##.#.\n"));
+        let smudge = patterns[1].smudge().unwrap();
+        assert_eq!(
+            (smudge.position, smudge.axis),
+            ((4, 0), Axis::Horizontal(1))
+        );
+    }
 }
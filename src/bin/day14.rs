@@ -1,4 +1,7 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+};
 
 use aoc2023::{read_input, InputType};
 use timed::timed;
@@ -10,135 +13,221 @@ fn main() {
 
     println!("Part1: {}", part1(&input));
     println!("Part2: {}", part2(&input));
+    if let Some(sequence) = std::env::args().skip_while(|arg| arg != "--tilts").nth(1) {
+        let tilts = parse_tilts(&sequence).unwrap();
+        let load = parse(&input).unwrap().load_after(&tilts, 1_000_000_000);
+        println!("{}: {}", sequence, load);
+    }
 }
 
-fn parse(input: &str) -> Vec<Vec<char>> {
-    input
-        .lines()
-        .map(|line| line.chars().collect::<Vec<_>>())
-        .collect::<Vec<_>>()
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tilt {
+    North,
+    West,
+    South,
+    East,
 }
-
-fn move_north(map: &mut Vec<Vec<char>>, mut position: (usize, usize)) {
-    if position.1 == 0 || map[position.1 - 1][position.0] != '.' {
-        return;
+impl Tilt {
+    fn vertical(&self) -> bool {
+        matches!(self, Tilt::North | Tilt::South)
     }
-    map[position.1][position.0] = '.';
-    position.1 -= 1;
-    map[position.1][position.0] = 'O';
-    move_north(map, position);
 }
-fn move_west(map: &mut Vec<Vec<char>>, mut position: (usize, usize)) {
-    if position.0 == 0 || map[position.1][position.0 - 1] != '.' {
-        return;
-    }
-    map[position.1][position.0] = '.';
-    position.0 -= 1;
-    map[position.1][position.0] = 'O';
-    move_west(map, position);
+
+fn parse_tilts(sequence: &str) -> Result<Vec<Tilt>, String> {
+    sequence
+        .chars()
+        .map(|x| match x {
+            'N' => Ok(Tilt::North),
+            'W' => Ok(Tilt::West),
+            'S' => Ok(Tilt::South),
+            'E' => Ok(Tilt::East),
+            _ => Err(format!("unknown tilt `{}`", x)),
+        })
+        .collect()
 }
-fn move_south(map: &mut Vec<Vec<char>>, mut position: (usize, usize)) {
-    if position.1 == map.len() - 1 || map[position.1 + 1][position.0] != '.' {
-        return;
-    }
-    map[position.1][position.0] = '.';
-    position.1 += 1;
-    map[position.1][position.0] = 'O';
-    move_south(map, position);
+
+/// Run of free cells between cube rocks, as bits of a row or column.
+#[derive(Debug, Clone, Copy)]
+struct Segment {
+    start: u32,
+    length: u32,
+    mask: u128,
 }
-fn move_east(map: &mut Vec<Vec<char>>, mut position: (usize, usize)) {
-    if position.0 == map[0].len() - 1 || map[position.1][position.0 + 1] != '.' {
-        return;
-    }
-    map[position.1][position.0] = '.';
-    position.0 += 1;
-    map[position.1][position.0] = 'O';
-    move_east(map, position);
+
+fn low_bits(count: u32) -> u128 {
+    u128::MAX.checked_shr(128 - count).unwrap_or(0)
 }
 
-#[timed]
-fn part1(input: &str) -> usize {
-    let mut map = parse(input);
-    for y in 1..map.len() {
-        for x in 0..map[0].len() {
-            if map[y][x] == 'O' {
-                move_north(&mut map, (x, y));
-            }
+fn segments(cubes: u128, length: usize) -> Vec<Segment> {
+    let mut result = vec![];
+    let mut start = 0;
+    for end in (0..=length as u32).filter(|&i| i == length as u32 || cubes >> i & 1 == 1) {
+        if end > start {
+            let mask = low_bits(end - start) << start;
+            result.push(Segment {
+                start,
+                length: end - start,
+                mask,
+            });
         }
+        start = end + 1;
     }
-    let line_count = map.len();
-    map.into_iter().enumerate().fold(0, |acc, (index, x)| {
-        acc + x.into_iter().filter(|&x| x == 'O').count() * (line_count - index)
+    result
+}
+
+/// Rolls the rocks of a line towards bit 0, or away from it, one segment at a time.
+fn roll(line: u128, segments: &[Segment], towards_start: bool) -> u128 {
+    segments.iter().fold(0, |acc, segment| {
+        let count = (line & segment.mask).count_ones();
+        let offset = if towards_start {
+            segment.start
+        } else {
+            segment.start + segment.length - count
+        };
+        acc | low_bits(count) << offset
     })
 }
 
-fn move_cycle(
-    map: &Vec<Vec<char>>,
-    mem: &mut HashMap<Vec<Vec<char>>, Vec<Vec<char>>>,
-) -> Vec<Vec<char>> {
-    if mem.contains_key(map) {
-        return mem.get(map).unwrap().clone();
+fn transpose(lines: &[u128], size: usize) -> Vec<u128> {
+    let mut result = vec![0; size];
+    for (i, &line) in lines.iter().enumerate() {
+        let mut bits = line;
+        while bits != 0 {
+            result[bits.trailing_zeros() as usize] |= 1 << i;
+            bits &= bits - 1;
+        }
     }
-    let mut map_copy = map.clone();
-    for y in 1..map_copy.len() {
-        for x in 0..map_copy[0].len() {
-            if map_copy[y][x] == 'O' {
-                move_north(&mut map_copy, (x, y));
+    result
+}
+
+/// Bitboard of the platform, bit x of row y is the cell at (x, y).
+#[derive(Debug, Clone)]
+struct Platform {
+    width: usize,
+    height: usize,
+    row_segments: Vec<Vec<Segment>>,
+    column_segments: Vec<Vec<Segment>>,
+    cubes: Vec<u128>,
+    rounds: Vec<u128>,
+}
+impl Platform {
+    fn tilt(&mut self, tilts: &[Tilt]) {
+        let mut by_columns = false;
+        for &tilt in tilts {
+            if tilt.vertical() != by_columns {
+                let size = if by_columns { self.height } else { self.width };
+                self.rounds = transpose(&self.rounds, size);
+                by_columns = tilt.vertical();
+            }
+            let segments = if by_columns {
+                &self.column_segments
+            } else {
+                &self.row_segments
+            };
+            let towards_start = matches!(tilt, Tilt::North | Tilt::West);
+            for (line, segments) in self.rounds.iter_mut().zip(segments) {
+                *line = roll(*line, segments, towards_start);
             }
         }
+        if by_columns {
+            self.rounds = transpose(&self.rounds, self.height);
+        }
     }
-    for x in 1..map_copy[0].len() {
-        for y in 0..map_copy.len() {
-            if map_copy[y][x] == 'O' {
-                move_west(&mut map_copy, (x, y));
+
+    fn north_load(&self) -> usize {
+        self.rounds
+            .iter()
+            .enumerate()
+            .map(|(y, row)| row.count_ones() as usize * (self.height - y))
+            .sum()
+    }
+
+    /// Load after repeating the tilts `cycles` times, skipping ahead once a state repeats.
+    fn load_after(&self, tilts: &[Tilt], cycles: usize) -> usize {
+        let mut platform = self.clone();
+        let mut seen = HashMap::new();
+        let mut loads = vec![];
+        for cycle in 0..cycles {
+            if let Some(&start) = seen.get(&platform.rounds) {
+                return loads[start + (cycles - cycle) % (cycle - start)];
             }
+            seen.insert(platform.rounds.clone(), cycle);
+            loads.push(platform.north_load());
+            platform.tilt(tilts);
         }
+        platform.north_load()
     }
-    for y in (0..map_copy.len() - 1).rev() {
-        for x in 0..map_copy[0].len() {
-            if map_copy[y][x] == 'O' {
-                move_south(&mut map_copy, (x, y));
+}
+impl Display for Platform {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        for (cubes, rounds) in self.cubes.iter().zip(self.rounds.iter()) {
+            for x in 0..self.width {
+                let symbol = match (cubes >> x & 1, rounds >> x & 1) {
+                    (1, _) => '#',
+                    (_, 1) => 'O',
+                    _ => '.',
+                };
+                write!(f, "{}", symbol)?;
             }
+            writeln!(f)?;
         }
+        Ok(())
+    }
+}
+
+fn parse(input: &str) -> Result<Platform, String> {
+    let map = input
+        .lines()
+        .map(|line| line.chars().collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let (width, height) = (map.first().map_or(0, |x| x.len()), map.len());
+    if width > 128 || height > 128 {
+        return Err(format!(
+            "platform {}x{} is over 128 cells wide",
+            width, height
+        ));
     }
-    for x in (0..map_copy[0].len() - 1).rev() {
-        for y in 0..map_copy.len() {
-            if map_copy[y][x] == 'O' {
-                move_east(&mut map_copy, (x, y));
+    let mut cubes = vec![0u128; height];
+    let mut rounds = vec![0u128; height];
+    for (y, line) in map.iter().enumerate() {
+        if line.len() != width {
+            return Err(format!("row {} is not {} cells long", y, width));
+        }
+        for (x, &cell) in line.iter().enumerate() {
+            match cell {
+                '#' => cubes[y] |= 1 << x,
+                'O' => rounds[y] |= 1 << x,
+                '.' => {}
+                _ => return Err(format!("unknown cell `{}`", cell)),
             }
         }
     }
-    mem.insert(map.to_vec(), map_copy.clone());
-    return map_copy;
+    let row_segments = cubes.iter().map(|&x| segments(x, width)).collect();
+    let column_segments = transpose(&cubes, width)
+        .into_iter()
+        .map(|x| segments(x, height))
+        .collect();
+    Ok(Platform {
+        width,
+        height,
+        row_segments,
+        column_segments,
+        cubes,
+        rounds,
+    })
 }
 
 #[timed]
-fn part2(input: &str) -> usize {
-    let mut map = parse(input);
-    let mut mem = HashMap::new();
-    let line_count = map.len();
-    let n = 500;
-    let first_n = (1..=n)
-        .map(|_| {
-            map = move_cycle(&map, &mut mem);
-            map.iter().enumerate().fold(0, |acc, (index, x)| {
-                acc + x.into_iter().filter(|&&x| x == 'O').count() * (line_count - index)
-            })
-        })
-        .collect::<Vec<_>>();
-
-    let sample_size = 100;
-    let samples = first_n[n - sample_size..].to_vec();
-
-    let mut cycle_len = 2;
-    let mut cycle = samples[..cycle_len].to_vec();
-    while cycle != samples[cycle_len..cycle_len * 2].to_vec() {
-        cycle_len += 1;
-        cycle = samples[..cycle_len].to_vec();
-    }
-    let index = (1_000_000_000 - ((n - sample_size) + 1)) % cycle_len;
+fn part1(input: &str) -> usize {
+    let mut platform = parse(input).unwrap();
+    platform.tilt(&[Tilt::North]);
+    platform.north_load()
+}
 
-    cycle[index]
+#[timed]
+fn part2(input: &str) -> usize {
+    let tilts = parse_tilts("NWSE").unwrap();
+    parse(input).unwrap().load_after(&tilts, 1_000_000_000)
 }
 
 #[cfg(test)]
@@ -190,4 +279,35 @@ mod tests {
         let result = part2(&get_test_input(InputType::Other("WJ")));
         assert_eq!(result, expected);
     }
+
+    #[test]
+    fn tilt_test() {
+        let mut platform = parse(&get_test_input(InputType::Test)).unwrap();
+        platform.tilt(&parse_tilts("NWSE").unwrap());
+        let expected = ".....#....\n....#...O#\n...OO##...\n.OO#......\n.....OOO#.\n.O#...O#.#\n....O#....\n......OOOO\n#...O###..\n#..OO#....\n";
+        assert_eq!(platform.to_string(), expected);
+
+        let mut platform = parse(&get_test_input(InputType::Test)).unwrap();
+        platform.tilt(&parse_tilts("NNE").unwrap());
+        let mut expected = parse(&get_test_input(InputType::Test)).unwrap();
+        expected.tilt(&[Tilt::North]);
+        expected.tilt(&[Tilt::East]);
+        assert_eq!(platform.rounds, expected.rounds);
+        assert_eq!(platform.load_after(&[Tilt::North, Tilt::East], 0), 136);
+        for sequence in ["NWSE", "NNE", "ESW"] {
+            let tilts = parse_tilts(sequence).unwrap();
+            let mut platform = parse(&get_test_input(InputType::Test)).unwrap();
+            let loads = (0..40)
+                .map(|_| {
+                    platform.tilt(&tilts);
+                    platform.north_load()
+                })
+                .collect::<Vec<_>>();
+            let platform = parse(&get_test_input(InputType::Test)).unwrap();
+            for (cycles, load) in (1..=40).zip(loads) {
+                assert_eq!(platform.load_after(&tilts, cycles), load);
+            }
+        }
+        assert_eq!(parse_tilts("NX"), Err("unknown tilt `X`".to_string()));
+    }
 }
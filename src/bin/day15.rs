@@ -1,6 +1,10 @@
-use std::usize;
+use std::{
+    fmt::{self, Display, Formatter},
+    hash::{BuildHasher, BuildHasherDefault, Hasher},
+};
 
 use aoc2023::{read_input, InputType};
+use itertools::Itertools;
 use timed::timed;
 
 const DAY: u8 = 15;
@@ -9,12 +13,44 @@ fn main() {
 
     println!("Part1: {}", part1(&input));
     println!("Part2: {}", part2(&input));
+    if std::env::args().any(|arg| arg == "--boxes") {
+        print!("{}", arrange(&input));
+    }
+    if std::env::args().any(|arg| arg == "--design") {
+        let mut configuration = vec![vec![]; 256];
+        for (index, _, _, &focal_length) in arrange(&input).iter() {
+            configuration[index].push(focal_length);
+        }
+        let charset = "abcdefghijklmnopqrstuvwxyz";
+        println!("{}", design(&configuration, charset, 4).unwrap());
+        println!("Box 0 labels: {}", sample(0, charset, 6, 5, 15).join(", "));
+    }
+}
+
+/// The HASH algorithm as a `Hasher`. Keys hashed through `Hash` also feed their
+/// terminators, so use `write` directly to get the puzzle's values.
+#[derive(Debug, Default, Clone, Copy)]
+struct HashHasher {
+    state: u8,
+}
+impl Hasher for HashHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.state = self.state.wrapping_add(byte).wrapping_mul(17);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.state as u64
+    }
 }
 
+type HashBuilder = BuildHasherDefault<HashHasher>;
+
 fn hash(input: &str) -> usize {
-    input
-        .chars()
-        .fold(0, |acc, x| ((acc + x as usize) * 17) % 256)
+    let mut hasher = HashBuilder::default().build_hasher();
+    hasher.write(input.as_bytes());
+    hasher.finish() as usize
 }
 
 #[timed]
@@ -30,17 +66,81 @@ struct Step<'a> {
     label: &'a str,
     command: Command,
 }
+/// HASHMAP, 256 boxes keeping their entries in insertion order.
 #[derive(Debug, Clone)]
-struct Lens<'a> {
-    label: &'a str,
-    focal_length: usize,
+struct LensMap<V, S = HashBuilder> {
+    boxes: Vec<Vec<(String, V)>>,
+    hasher: S,
+}
+impl<V> LensMap<V> {
+    fn new() -> LensMap<V> {
+        LensMap::with_hasher(HashBuilder::default())
+    }
+}
+impl<V, S: BuildHasher> LensMap<V, S> {
+    fn with_hasher(hasher: S) -> LensMap<V, S> {
+        LensMap {
+            boxes: (0..256).map(|_| vec![]).collect(),
+            hasher,
+        }
+    }
+
+    fn position(&self, label: &str) -> (usize, Option<usize>) {
+        let mut hasher = self.hasher.build_hasher();
+        hasher.write(label.as_bytes());
+        let index = hasher.finish() as usize % self.boxes.len();
+        let position = self.boxes[index].iter().position(|(x, _)| x == label);
+        (index, position)
+    }
+
+    /// Replaces the value in place or appends it to the back of its box.
+    fn insert(&mut self, label: &str, value: V) -> Option<V> {
+        match self.position(label) {
+            (index, Some(position)) => {
+                Some(std::mem::replace(&mut self.boxes[index][position].1, value))
+            }
+            (index, None) => {
+                self.boxes[index].push((label.to_string(), value));
+                None
+            }
+        }
+    }
+
+    fn remove(&mut self, label: &str) -> Option<V> {
+        let (index, position) = self.position(label);
+        position.map(|position| self.boxes[index].remove(position).1)
+    }
+
+    /// Entries as (box, slot, label, value), in box order.
+    fn iter(&self) -> impl Iterator<Item = (usize, usize, &str, &V)> {
+        self.boxes.iter().enumerate().flat_map(|(index, lens_box)| {
+            lens_box
+                .iter()
+                .enumerate()
+                .map(move |(slot, (label, value))| (index, slot, label.as_str(), value))
+        })
+    }
 }
-impl Lens<'_> {
-    fn new(label: &str, focal_length: usize) -> Lens {
-        Lens {
-            label,
-            focal_length,
+impl<V: Display, S> Display for LensMap<V, S> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        for (index, lens_box) in self.boxes.iter().enumerate() {
+            if lens_box.is_empty() {
+                continue;
+            }
+            write!(f, "Box {}:", index)?;
+            for (label, value) in lens_box {
+                write!(f, " [{} {}]", label, value)?;
+            }
+            writeln!(f)?;
         }
+        Ok(())
+    }
+}
+impl LensMap<usize> {
+    fn focusing_power(&self) -> usize {
+        self.iter()
+            .map(|(index, slot, _, focal_length)| (index + 1) * (slot + 1) * focal_length)
+            .sum()
     }
 }
 
@@ -59,46 +159,147 @@ fn parse_part2(input: &str) -> Vec<Step> {
         .collect::<Vec<_>>()
 }
 
-#[timed]
-fn part2(input: &str) -> usize {
-    let parsed = parse_part2(input);
-    let mut boxes: Vec<Vec<Lens>> = vec![vec![]; 256];
-    for step in parsed.iter() {
+fn arrange(input: &str) -> LensMap<usize> {
+    let mut lenses = LensMap::new();
+    for step in parse_part2(input) {
         match step.command {
             Command::Minus => {
-                boxes[hash(&step.label)] = boxes[hash(&step.label)]
-                    .clone()
-                    .into_iter()
-                    .filter(|lens| lens.label != step.label)
-                    .collect::<Vec<_>>()
+                lenses.remove(step.label);
             }
             Command::Equals(focal_length) => {
-                let index = boxes[hash(&step.label)]
-                    .iter()
-                    .position(|lens| lens.label == step.label);
-                match index {
-                    Some(index) => boxes[hash(&step.label)][index].focal_length = focal_length,
-                    None => boxes[hash(&step.label)].push(Lens::new(step.label, focal_length)),
-                }
+                lenses.insert(step.label, focal_length);
             }
         }
     }
-    boxes
-        .into_iter()
-        .enumerate()
-        .fold(0, |acc, (box_index, lens_box)| {
-            acc + lens_box
-                .into_iter()
-                .enumerate()
-                .fold(0, |acc, (lens_index, lens)| {
-                    acc + (box_index + 1) * (lens_index + 1) * lens.focal_length
+    lenses
+}
+
+#[timed]
+fn part2(input: &str) -> usize {
+    arrange(input).focusing_power()
+}
+
+/// 17 * 241 = 4097, so this undoes the multiplication of a HASH step.
+const INVERSE: u8 = 241;
+
+fn unhash(mut state: u8, label: &[u8]) -> u8 {
+    for &byte in label.iter().rev() {
+        state = state.wrapping_mul(INVERSE).wrapping_sub(byte);
+    }
+    state
+}
+
+fn strings(charset: &[u8], length: usize) -> Vec<Vec<u8>> {
+    (0..length).fold(vec![vec![]], |acc, _| {
+        acc.into_iter()
+            .cartesian_product(charset.iter())
+            .map(|(mut string, &byte)| {
+                string.push(byte);
+                string
+            })
+            .collect()
+    })
+}
+
+/// Splits the labels of one length in halves, bucketing both by the state between them.
+/// The prefixes are run forwards from 0 and the suffixes backwards from the target.
+fn halves(target: u8, charset: &[u8], length: usize) -> [Vec<Vec<Vec<u8>>>; 2] {
+    let mut prefixes = vec![vec![]; 256];
+    for prefix in strings(charset, length / 2) {
+        let mut hasher = HashHasher::default();
+        hasher.write(&prefix);
+        prefixes[hasher.finish() as usize].push(prefix);
+    }
+    let mut suffixes = vec![vec![]; 256];
+    for suffix in strings(charset, length - length / 2) {
+        suffixes[unhash(target, &suffix) as usize].push(suffix);
+    }
+    [prefixes, suffixes]
+}
+
+/// Every label up to `max_length` characters from `charset` landing in box `target`,
+/// shortest first.
+fn labels(target: u8, charset: &str, max_length: usize) -> impl Iterator<Item = String> {
+    let charset = charset.bytes().collect::<Vec<_>>();
+    (1..=max_length).flat_map(move |length| {
+        let [prefixes, suffixes] = halves(target, &charset, length);
+        prefixes
+            .into_iter()
+            .zip(suffixes)
+            .flat_map(|(prefixes, suffixes)| prefixes.into_iter().cartesian_product(suffixes))
+            .map(|(prefix, suffix)| String::from_utf8([prefix, suffix].concat()).unwrap())
+    })
+}
+
+fn next_random(state: &mut u64) -> u64 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    *state
+}
+
+/// Uniformly samples labels of exactly `length` characters landing in box `target`.
+fn sample(target: u8, charset: &str, length: usize, count: usize, seed: u64) -> Vec<String> {
+    let [prefixes, suffixes] = halves(target, charset.as_bytes(), length);
+    let weights = prefixes
+        .iter()
+        .zip(suffixes.iter())
+        .map(|(prefixes, suffixes)| (prefixes.len() * suffixes.len()) as u64)
+        .collect::<Vec<_>>();
+    let total = weights.iter().sum::<u64>();
+    if total == 0 {
+        return vec![];
+    }
+    let mut state = seed.max(1);
+    (0..count)
+        .map(|_| {
+            let mut pick = next_random(&mut state) % total;
+            let middle = weights
+                .iter()
+                .position(|&weight| {
+                    pick = pick.wrapping_sub(weight);
+                    pick >= total
                 })
+                .unwrap();
+            let prefix =
+                &prefixes[middle][next_random(&mut state) as usize % prefixes[middle].len()];
+            let suffix =
+                &suffixes[middle][next_random(&mut state) as usize % suffixes[middle].len()];
+            String::from_utf8([prefix.as_slice(), suffix].concat()).unwrap()
         })
+        .collect()
+}
+
+/// Steps inserting fresh labels so that box `i` ends up holding `configuration[i]`.
+fn design(
+    configuration: &[Vec<usize>],
+    charset: &str,
+    max_length: usize,
+) -> Result<String, String> {
+    if configuration.len() > 256 {
+        return Err(format!(
+            "there are only 256 boxes, got {}",
+            configuration.len()
+        ));
+    }
+    let mut steps = vec![];
+    for (index, focal_lengths) in configuration.iter().enumerate() {
+        let names = labels(index as u8, charset, max_length).take(focal_lengths.len());
+        let names = names.collect::<Vec<_>>();
+        if names.len() < focal_lengths.len() {
+            return Err(format!("not enough labels for box {}", index));
+        }
+        for (label, focal_length) in names.into_iter().zip(focal_lengths) {
+            steps.push(format!("{}={}", label, focal_length));
+        }
+    }
+    Ok(steps.join(","))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashMap;
 
     fn get_test_input(input_type: InputType) -> String {
         read_input(DAY, input_type).unwrap()
@@ -117,4 +318,79 @@ mod tests {
         let result = part2(&get_test_input(InputType::Test));
         assert_eq!(result, expected);
     }
+
+    #[test]
+    fn lens_map_test() {
+        assert_eq!(hash("HASH"), 52);
+        let mut lenses = LensMap::new();
+        assert_eq!(lenses.insert("rn", 1), None);
+        assert_eq!(lenses.insert("cm", 2), None);
+        assert_eq!(lenses.insert("qp", 3), None);
+        assert_eq!(lenses.remove("cm"), Some(2));
+        assert_eq!(lenses.insert("pc", 4), None);
+        assert_eq!(lenses.insert("rn", 5), Some(1));
+        assert_eq!(lenses.remove("ab"), None);
+        let entries = lenses
+            .iter()
+            .map(|(index, slot, label, _)| (index, slot, label));
+        assert_eq!(
+            entries.collect::<Vec<_>>(),
+            vec![(0, 0, "rn"), (1, 0, "qp"), (3, 0, "pc")]
+        );
+
+        let lenses = arrange(&get_test_input(InputType::Test));
+        assert_eq!(
+            lenses.to_string(),
+            "Box 0: [rn 1] [cm 2]\nBox 3: [ot 7] [ab 5] [pc 6]\n"
+        );
+    }
+
+    #[test]
+    fn hasher_test() {
+        let mut map: HashMap<&str, usize, HashBuilder> = HashMap::default();
+        for (index, label) in ["rn", "cm", "qp", "pc", "ot", "ab"].into_iter().enumerate() {
+            map.insert(label, index);
+        }
+        assert_eq!(map.get("pc"), Some(&3));
+        assert_eq!(map.len(), 6);
+    }
+
+    #[test]
+    fn labels_test() {
+        let found = labels(3, "abc", 5).collect::<Vec<_>>();
+        let expected = (1..=5)
+            .flat_map(|length| strings(b"abc", length))
+            .map(|x| String::from_utf8(x).unwrap())
+            .filter(|x| hash(x) == 3)
+            .collect::<Vec<_>>();
+        assert!(!found.is_empty());
+        assert_eq!(
+            found.iter().sorted().collect_vec(),
+            expected.iter().sorted().collect_vec()
+        );
+        assert!(labels(0, "cmnr", 2).contains(&"rn".to_string()));
+
+        let sampled = sample(3, "abcdefghijklmnopqrstuvwxyz", 6, 20, 7);
+        assert_eq!(sampled.len(), 20);
+        assert!(sampled.iter().all(|x| x.len() == 6 && hash(x) == 3));
+        assert!(sample(3, "a", 1, 5, 7).is_empty());
+    }
+
+    #[test]
+    fn design_test() {
+        let mut configuration = vec![vec![]; 4];
+        configuration[0] = vec![1, 2];
+        configuration[3] = vec![7, 5, 6];
+        let steps = design(&configuration, "abcdefghijklmnopqrstuvwxyz", 3).unwrap();
+        assert_eq!(part2(&steps), 145);
+        let lenses = arrange(&steps)
+            .iter()
+            .map(|(index, _, _, &focal_length)| (index, focal_length))
+            .collect::<Vec<_>>();
+        assert_eq!(lenses, vec![(0, 1), (0, 2), (3, 7), (3, 5), (3, 6)]);
+        assert_eq!(
+            design(&configuration, "a", 1),
+            Err("not enough labels for box 0".to_string())
+        );
+    }
 }
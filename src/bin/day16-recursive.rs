@@ -1,6 +1,6 @@
 use std::{collections::HashSet, usize};
 
-use aoc2023::{read_input, InputType};
+use aoc2023::{day16::Contraption, read_input, InputType};
 use timed::timed;
 
 const DAY: u8 = 16;
@@ -144,47 +144,9 @@ fn part1(input: &str) -> usize {
     visited.len()
 }
 
-fn calculate(map: &Vec<Vec<char>>, starting_point: Point, starting_direction: Direction) -> usize {
-    let mut points_directions = HashSet::new();
-    propagate(
-        map,
-        &mut points_directions,
-        starting_point,
-        starting_direction,
-    );
-    let mut visited = HashSet::new();
-    for (p, _) in points_directions.iter() {
-        visited.insert(p);
-    }
-    visited.len()
-}
 #[timed]
 fn part2(input: &str) -> usize {
-    let map = parse(input);
-    let mut result = 0;
-    // up / down
-    for x in 0..map[0].len() {
-        let tmp = calculate(&map, Point::new(x, 0), Direction::Down);
-        if tmp > result {
-            result = tmp
-        }
-        let tmp = calculate(&map, Point::new(x, map.len() - 1), Direction::Up);
-        if tmp > result {
-            result = tmp
-        }
-    }
-    // left / right
-    for y in 0..map[0].len() {
-        let tmp = calculate(&map, Point::new(0, y), Direction::Right);
-        if tmp > result {
-            result = tmp
-        }
-        let tmp = calculate(&map, Point::new(map[0].len() - 1, y), Direction::Left);
-        if tmp > result {
-            result = tmp
-        }
-    }
-    result
+    Contraption::new(input).max_energised()
 }
 
 #[cfg(test)]
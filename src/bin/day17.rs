@@ -1,8 +1,3 @@
-use std::{
-    cmp::Ordering,
-    collections::{BinaryHeap, HashSet},
-};
-
 use aoc2023::{read_input, InputType};
 use timed::timed;
 
@@ -27,7 +22,21 @@ enum Direction {
     Down,
     Left,
 }
+const DIRECTIONS: [Direction; 4] = [
+    Direction::Up,
+    Direction::Right,
+    Direction::Down,
+    Direction::Left,
+];
+
 impl Direction {
+    fn turns(&self) -> [Direction; 2] {
+        match self {
+            Direction::Up | Direction::Down => [Direction::Left, Direction::Right],
+            Direction::Right | Direction::Left => [Direction::Up, Direction::Down],
+        }
+    }
+
     fn apply_dir(
         &self,
         mut point: (usize, usize),
@@ -64,6 +73,28 @@ impl Direction {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Crucible {
+    min_run: u8,
+    max_run: u8,
+    turn_penalty: u32,
+}
+impl Crucible {
+    fn new(min_run: u8, max_run: u8) -> Crucible {
+        Crucible {
+            min_run,
+            max_run,
+            turn_penalty: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Route {
+    heat_loss: u32,
+    path: Vec<(usize, usize)>,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 struct Node {
     position: (usize, usize),
@@ -78,113 +109,104 @@ impl Node {
             subsequential,
         }
     }
-}
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-struct DistNode {
-    node: Node,
-    distance: u32,
-}
-impl DistNode {
-    fn new(node: Node, distance: u32) -> DistNode {
-        DistNode { node, distance }
-    }
-}
-impl Ord for DistNode {
-    fn cmp(&self, other: &Self) -> Ordering {
-        other.distance.cmp(&self.distance)
-    }
-}
-impl PartialOrd for DistNode {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
-    }
-}
 
-fn add_next(
-    map: &Vec<Vec<u32>>,
-    queue: &mut BinaryHeap<DistNode>,
-    visited: &mut HashSet<Node>,
-    prev: DistNode,
-    direction: Direction,
-) {
-    let subsequential = if prev.node.direction == direction {
-        prev.node.subsequential + 1
-    } else {
-        1
-    };
-    if let Some(next) = direction.apply_dir(prev.node.position, map[0].len() - 1, map.len() - 1) {
-        let node = Node::new(next, direction, subsequential);
-        if !visited.contains(&node) {
-            visited.insert(node);
-            queue.push(DistNode::new(node, prev.distance + map[next.1][next.0]));
-        }
+    fn index(&self, width: usize, max_run: u8) -> usize {
+        let cell = self.position.1 * width + self.position.0;
+        (cell * 4 + self.direction as usize) * (max_run as usize + 1) + self.subsequential as usize
     }
 }
-#[timed]
-fn part1(input: &str) -> u32 {
-    let map = parse(input);
+
+/// Dijkstra over (position, direction, run) states with a circular bucket queue, since
+/// no step costs more than a digit plus the turn penalty.
+fn solve(
+    map: &[Vec<u32>],
+    crucible: Crucible,
+    source: (usize, usize),
+    goal: (usize, usize),
+) -> Option<Route> {
     let max_x = map[0].len() - 1;
     let max_y = map.len() - 1;
-    let mut queue = BinaryHeap::new();
-    let mut visited = HashSet::new();
-    queue.push(DistNode::new(Node::new((0, 0), Direction::Right, 0), 0));
-    queue.push(DistNode::new(Node::new((0, 0), Direction::Down, 0), 0));
-    let mut result = 0;
-    while !queue.is_empty() {
-        let prev = queue.pop().unwrap();
-        if prev.node.position == (max_x, max_y) {
-            result = prev.distance;
-            break;
+    let width = map[0].len();
+    let index = |node: &Node| node.index(width, crucible.max_run);
+    let states = width * map.len() * 4 * (crucible.max_run as usize + 1);
+    let mut distance = vec![u32::MAX; states];
+    let mut parent: Vec<Option<Node>> = vec![None; states];
+    let size = 10 + crucible.turn_penalty as usize;
+    let mut buckets = vec![vec![]; size];
+    let mut queued = 0;
+    for direction in DIRECTIONS {
+        let node = Node::new(source, direction, 0);
+        distance[index(&node)] = 0;
+        buckets[0].push(node);
+        queued += 1;
+    }
+    let mut current = 0;
+    while queued > 0 {
+        let bucket = current as usize % size;
+        let Some(prev) = buckets[bucket].pop() else {
+            current += 1;
+            continue;
+        };
+        queued -= 1;
+        if distance[index(&prev)] < current {
+            continue;
         }
-        let need_turn = prev.node.subsequential == 3;
-        let direction = prev.node.direction;
-        if !(need_turn && direction == Direction::Up) && direction != Direction::Down {
-            add_next(&map, &mut queue, &mut visited, prev, Direction::Up);
+        if prev.position == goal && (prev.subsequential >= crucible.min_run || source == goal) {
+            let mut path = vec![prev.position];
+            let mut node = prev;
+            while let Some(previous) = parent[index(&node)] {
+                path.push(previous.position);
+                node = previous;
+            }
+            path.reverse();
+            return Some(Route {
+                heat_loss: current,
+                path,
+            });
         }
-        if !(need_turn && direction == Direction::Right) && direction != Direction::Left {
-            add_next(&map, &mut queue, &mut visited, prev, Direction::Right);
+
+        let mut moves = vec![];
+        if prev.subsequential < crucible.max_run {
+            moves.push((prev.direction, prev.subsequential + 1, 0));
         }
-        if !(need_turn && direction == Direction::Down) && direction != Direction::Up {
-            add_next(&map, &mut queue, &mut visited, prev, Direction::Down);
+        if prev.subsequential >= crucible.min_run {
+            for direction in prev.direction.turns() {
+                moves.push((direction, 1, crucible.turn_penalty));
+            }
         }
-        if !(need_turn && direction == Direction::Left) && direction != Direction::Right {
-            add_next(&map, &mut queue, &mut visited, prev, Direction::Left);
+        for (direction, subsequential, penalty) in moves {
+            let Some(next) = direction.apply_dir(prev.position, max_x, max_y) else {
+                continue;
+            };
+            let node = Node::new(next, direction, subsequential);
+            let cost = current + map[next.1][next.0] + penalty;
+            if cost < distance[index(&node)] {
+                distance[index(&node)] = cost;
+                parent[index(&node)] = Some(prev);
+                buckets[cost as usize % size].push(node);
+                queued += 1;
+            }
         }
     }
-    result
+    None
+}
+
+#[timed]
+fn part1(input: &str) -> u32 {
+    let map = parse(input);
+    let goal = (map[0].len() - 1, map.len() - 1);
+    solve(&map, Crucible::new(1, 3), (0, 0), goal)
+        .unwrap()
+        .heat_loss
 }
 
 #[timed]
 fn part2(input: &str) -> u32 {
     let map = parse(input);
-    let max_x = map[0].len() - 1;
-    let max_y = map.len() - 1;
-    let mut queue = BinaryHeap::new();
-    let mut visited = HashSet::new();
-    queue.push(DistNode::new(Node::new((0, 0), Direction::Right, 0), 0));
-    queue.push(DistNode::new(Node::new((0, 0), Direction::Down, 0), 0));
-    let mut result = 0;
-    while !queue.is_empty() {
-        let prev = queue.pop().unwrap();
-        if prev.node.position == (max_x, max_y) && prev.node.subsequential >= 4 {
-            result = prev.distance;
-            break;
-        }
-        if prev.node.subsequential < 10 {
-            add_next(&map, &mut queue, &mut visited, prev, prev.node.direction);
-        }
-        if prev.node.subsequential >= 4 {
-            if !matches!(prev.node.direction, Direction::Down | Direction::Up) {
-                add_next(&map, &mut queue, &mut visited, prev, Direction::Up);
-                add_next(&map, &mut queue, &mut visited, prev, Direction::Down);
-            }
-            if !matches!(prev.node.direction, Direction::Left | Direction::Right) {
-                add_next(&map, &mut queue, &mut visited, prev, Direction::Right);
-                add_next(&map, &mut queue, &mut visited, prev, Direction::Left);
-            }
-        }
-    }
-    result
+    let goal = (map[0].len() - 1, map.len() - 1);
+    solve(&map, Crucible::new(4, 10), (0, 0), goal)
+        .unwrap()
+        .heat_loss
 }
 
 #[cfg(test)]
@@ -222,4 +244,32 @@ mod tests {
         let result = part2(&get_test_input(InputType::Other("test2")));
         assert_eq!(result, expected);
     }
+
+    #[test]
+    fn route_test() {
+        let map = parse(&get_test_input(InputType::Test));
+        let route = solve(&map, Crucible::new(4, 10), (0, 0), (12, 12)).unwrap();
+        assert_eq!(route.heat_loss, 94);
+        assert_eq!(route.path.first(), Some(&(0, 0)));
+        assert_eq!(route.path.last(), Some(&(12, 12)));
+        let heat_loss = route.path[1..].iter().map(|&(x, y)| map[y][x]).sum::<u32>();
+        assert_eq!(heat_loss, 94);
+    }
+
+    #[test]
+    fn turn_penalty_test() {
+        let map = parse(&get_test_input(InputType::Test));
+        let crucible = Crucible::new(1, 3);
+        let free = solve(&map, crucible, (0, 0), (12, 12)).unwrap();
+        let penalised = Crucible {
+            turn_penalty: 5,
+            ..crucible
+        };
+        let route = solve(&map, penalised, (0, 0), (12, 12)).unwrap();
+        assert!(route.heat_loss > free.heat_loss);
+
+        let route = solve(&map, crucible, (12, 12), (0, 0)).unwrap();
+        assert_eq!(route.path.last(), Some(&(0, 0)));
+        assert_eq!(solve(&map, crucible, (3, 3), (3, 3)).unwrap().heat_loss, 0);
+    }
 }
@@ -1,4 +1,4 @@
-use aoc2023::{read_input, InputType};
+use aoc2023::{polygon::Polygon, read_input, InputType};
 use timed::timed;
 
 const DAY: u8 = 18;
@@ -69,18 +69,12 @@ impl Command {
 
 fn calculate_area(commands: Vec<Command>) -> usize {
     let mut points = vec![(0, 0)];
-    let mut b = 0;
     for command in commands {
         let last = points.last().unwrap();
         points.push(command.direction.apply_dir(*last, command.steps));
-        b += command.steps as usize;
     }
-    let mut area = 0;
-    for pair in points.windows(2) {
-        area += (pair[0].0 * pair[1].1) - (pair[1].0 * pair[0].1);
-    }
-    area /= 2;
-    area as usize + 1 + b / 2
+    let polygon = Polygon::new(&points).unwrap();
+    (polygon.interior_points() + polygon.boundary_points()) as usize
 }
 
 #[timed]
@@ -116,4 +110,11 @@ mod tests {
         let result = part2(&get_test_input(InputType::Test));
         assert_eq!(result, expected);
     }
+
+    #[test]
+    fn empty_interior_test() {
+        let expected = 6;
+        let result = part1("R 2 (#000000)\nD 1 (#000000)\nL 2 (#000000)\nU 1 (#000000)");
+        assert_eq!(result, expected);
+    }
 }
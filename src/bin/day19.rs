@@ -1,154 +1,686 @@
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{self, Display, Formatter},
+};
 
 use aoc2023::{read_input, InputType};
+use itertools::Itertools;
 use timed::timed;
 
 const DAY: u8 = 19;
 const NEWLINE_CHAR: &str = "\n";
+type Ranges = Vec<(usize, usize)>;
 fn main() {
     let input = read_input(DAY, InputType::Real).unwrap();
+    if std::env::args().any(|arg| arg == "--analyse") {
+        let (workflows, parts) = parse(&input);
+        let report = analyse(&workflows, &categories(&parts), "in").unwrap();
+        print!("{}", report);
+        println!();
+        for workflow in simplify(&workflows, &report, "in") {
+            println!("{}", workflow);
+        }
+        return;
+    }
+
+    match part1(&input) {
+        Ok(result) => println!("Part1: {}", result),
+        Err(error) => println!("Part1 failed: {}", error),
+    }
+    match part2(&input) {
+        Ok(result) => println!("Part2: {}", result),
+        Err(error) => println!("Part2 failed: {}", error),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    Less,
+    Greater,
+    LessEqual,
+    GreaterEqual,
+    Equal,
+}
+impl Operator {
+    fn parse(input: &str) -> Option<(Operator, &str)> {
+        [
+            ("<=", Operator::LessEqual),
+            (">=", Operator::GreaterEqual),
+            ("<", Operator::Less),
+            (">", Operator::Greater),
+            ("=", Operator::Equal),
+        ]
+        .into_iter()
+        .find_map(|(symbol, operator)| input.strip_prefix(symbol).map(|rest| (operator, rest)))
+    }
+
+    fn symbol(&self) -> &'static str {
+        match self {
+            Operator::Less => "<",
+            Operator::Greater => ">",
+            Operator::LessEqual => "<=",
+            Operator::GreaterEqual => ">=",
+            Operator::Equal => "=",
+        }
+    }
+
+    fn test(&self, value: usize, number: usize) -> bool {
+        match self {
+            Operator::Less => value < number,
+            Operator::Greater => value > number,
+            Operator::LessEqual => value <= number,
+            Operator::GreaterEqual => value >= number,
+            Operator::Equal => value == number,
+        }
+    }
+
+    /// Splits an inclusive range into the part passing the test and the parts failing it.
+    fn split(
+        &self,
+        (start, end): (usize, usize),
+        number: usize,
+    ) -> (Option<(usize, usize)>, Ranges) {
+        let range = |start: Option<usize>, end: Option<usize>| match (start, end) {
+            (Some(start), Some(end)) if start <= end => Some((start, end)),
+            _ => None,
+        };
+        let below = range(Some(start), number.checked_sub(1).map(|x| x.min(end)));
+        let above = range(Some(start.max(number + 1)), Some(end));
+        let equal = range(Some(start.max(number)), Some(end.min(number)));
+        let lower = range(Some(start), Some(end.min(number)));
+        let upper = range(Some(start.max(number)), Some(end));
+        let (pass, fail) = match self {
+            Operator::Less => (below, vec![upper]),
+            Operator::Greater => (above, vec![lower]),
+            Operator::LessEqual => (lower, vec![above]),
+            Operator::GreaterEqual => (upper, vec![below]),
+            Operator::Equal => (equal, vec![below, above]),
+        };
+        (pass, fail.into_iter().flatten().collect())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Condition<'a> {
+    category: &'a str,
+    operator: Operator,
+    number: usize,
+}
 
-    println!("Part1: {}", part1(&input));
-    println!("Part2: {}", part2(&input));
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Rule<'a> {
+    condition: Option<Condition<'a>>,
+    destination: &'a str,
+}
+impl Rule<'_> {
+    fn parse(input: &str) -> Rule<'_> {
+        match input.split_once(':') {
+            Some((condition, destination)) => {
+                let split = condition.find(['<', '>', '=']).unwrap();
+                let (category, rest) = condition.split_at(split);
+                let (operator, number) = Operator::parse(rest).unwrap();
+                Rule {
+                    condition: Some(Condition {
+                        category,
+                        operator,
+                        number: number.parse().unwrap(),
+                    }),
+                    destination,
+                }
+            }
+            None => Rule {
+                condition: None,
+                destination: input,
+            },
+        }
+    }
+}
+
+impl Display for Rule<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match &self.condition {
+            Some(x) => write!(
+                f,
+                "{}{}{}:{}",
+                x.category,
+                x.operator.symbol(),
+                x.number,
+                self.destination
+            ),
+            None => write!(f, "{}", self.destination),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Workflow<'a> {
+    name: &'a str,
+    rules: Vec<Rule<'a>>,
+}
+impl Display for Workflow<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}{{{}}}", self.name, self.rules.iter().join(","))
+    }
 }
 
-fn parse(
-    input: &str,
-) -> (
-    HashMap<&str, Vec<((char, char, usize), &str)>>,
-    Vec<HashMap<char, usize>>,
-) {
+fn parse(input: &str) -> (Vec<Workflow<'_>>, Vec<HashMap<&str, usize>>) {
     let split_by = NEWLINE_CHAR.repeat(2);
-    let (workflow, categories) = input.split_once(&split_by).unwrap();
-    let workflow = workflow
+    let (workflows, parts) = input.split_once(&split_by).unwrap_or((input, ""));
+    let workflows = workflows
         .lines()
         .map(|line| {
-            let (key, rules) = line.split_once('{').unwrap();
-            let rules = rules
-                .split(',')
-                .map(|x| {
-                    let tmp = x.split_once(':');
-                    match tmp {
-                        Some((rule, destination)) => {
-                            let mut rule = rule.chars();
-                            let key = rule.next().unwrap();
-                            let operation = rule.next().unwrap();
-                            let number = rule.as_str().parse::<usize>().unwrap();
-                            ((key, operation, number), destination)
-                        }
-                        None => {
-                            let mut tmp = x.chars();
-                            tmp.next_back();
-                            (('.', '=', 1), tmp.as_str())
-                        }
-                    }
-                })
-                .collect::<Vec<_>>();
-            (key, rules)
+            let (name, rules) = line.split_once('{').unwrap();
+            let rules = rules.strip_suffix('}').unwrap();
+            Workflow {
+                name,
+                rules: rules.split(',').map(Rule::parse).collect(),
+            }
         })
-        .collect::<HashMap<_, _>>();
-    let categories = categories
+        .collect::<Vec<_>>();
+    let parts = parts
         .lines()
         .map(|line| {
-            let mut line = line.chars();
-            line.next();
-            line.next_back();
-            line.as_str()
-                .split(',')
+            let line = line.strip_prefix('{').unwrap().strip_suffix('}').unwrap();
+            line.split(',')
                 .map(|x| {
-                    let tmp = x.split_once('=').unwrap();
-                    (
-                        tmp.0.chars().next().unwrap(),
-                        tmp.1.parse::<usize>().unwrap(),
-                    )
+                    let (category, number) = x.split_once('=').unwrap();
+                    (category, number.parse::<usize>().unwrap())
                 })
                 .collect::<HashMap<_, _>>()
         })
         .collect::<Vec<_>>();
-    (workflow, categories)
+    (workflows, parts)
 }
 
-#[timed]
-fn part1(input: &str) -> usize {
-    let (workflows, to_eval) = parse(input);
-    let mut result = 0;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Target {
+    Accept,
+    Reject,
+    Workflow(usize),
+}
 
-    for categories in to_eval {
-        let mut current_workflow = "in";
-        while !matches!(current_workflow, "A" | "R") {
-            let tests = workflows.get(current_workflow).unwrap();
-            for ((key, operation, number), destination) in tests {
-                if key == &'.' {
-                    current_workflow = destination;
-                    break;
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CompiledRule {
+    condition: Option<(usize, Operator, usize)>,
+    target: Target,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EvaluateError<'a> {
+    /// The part went around a cycle of workflows.
+    Cycle,
+    /// No rule of the workflow matched the part.
+    NoFallback(&'a str),
+    /// A rule tested a category the part doesn't have.
+    MissingCategory(&'a str),
+}
+impl Display for EvaluateError<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            EvaluateError::Cycle => write!(f, "part never leaves a cycle of workflows"),
+            EvaluateError::NoFallback(name) => write!(f, "no rule of workflow `{}` matches", name),
+            EvaluateError::MissingCategory(name) => write!(f, "part has no `{}` category", name),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Compiled<'a> {
+    names: Vec<&'a str>,
+    categories: Vec<&'a str>,
+    workflows: Vec<Vec<CompiledRule>>,
+    start: usize,
+}
+impl<'a> Compiled<'a> {
+    fn new(
+        workflows: &[Workflow<'a>],
+        categories: &[&'a str],
+        start: &str,
+    ) -> Result<Compiled<'a>, String> {
+        let mut categories = categories.to_vec();
+        for workflow in workflows {
+            for condition in workflow.rules.iter().flat_map(|x| &x.condition) {
+                if !categories.contains(&condition.category) {
+                    categories.push(condition.category);
                 }
-                let value = categories.get(&key).unwrap();
+            }
+        }
+        let indices = workflows
+            .iter()
+            .enumerate()
+            .map(|(i, workflow)| (workflow.name, i))
+            .collect::<HashMap<_, _>>();
+        let target = |name: &str| match name {
+            "A" => Ok(Target::Accept),
+            "R" => Ok(Target::Reject),
+            _ => indices
+                .get(name)
+                .map(|&i| Target::Workflow(i))
+                .ok_or(format!("undefined workflow `{}`", name)),
+        };
+        let compiled = workflows
+            .iter()
+            .map(|workflow| {
+                workflow
+                    .rules
+                    .iter()
+                    .map(|rule| {
+                        Ok(CompiledRule {
+                            condition: rule.condition.as_ref().map(|x| {
+                                let index = categories.iter().position(|&c| c == x.category);
+                                (index.unwrap(), x.operator, x.number)
+                            }),
+                            target: target(rule.destination)?,
+                        })
+                    })
+                    .collect::<Result<Vec<_>, String>>()
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        let Target::Workflow(start) = target(start)? else {
+            return Err(format!("`{}` is not a workflow", start));
+        };
+        Ok(Compiled {
+            names: workflows.iter().map(|x| x.name).collect(),
+            categories,
+            workflows: compiled,
+            start,
+        })
+    }
 
-                let pass = match operation {
-                    '<' => value < &number,
-                    '>' => value > &number,
-                    _ => false,
-                };
-                if pass {
-                    current_workflow = destination;
+    /// Runs a part through the workflows, returning whether it is accepted.
+    fn evaluate(&self, part: &HashMap<&str, usize>) -> Result<bool, EvaluateError<'a>> {
+        let values = self
+            .categories
+            .iter()
+            .map(|x| part.get(x).copied())
+            .collect::<Vec<_>>();
+        let mut current = self.start;
+        for _ in 0..=self.workflows.len() {
+            let mut target = None;
+            for rule in self.workflows[current].iter() {
+                if let Some((index, operator, number)) = rule.condition {
+                    let value = values[index]
+                        .ok_or(EvaluateError::MissingCategory(self.categories[index]))?;
+                    if !operator.test(value, number) {
+                        continue;
+                    }
+                }
+                target = Some(rule.target);
+                break;
+            }
+            match target.ok_or(EvaluateError::NoFallback(self.names[current]))? {
+                Target::Accept => return Ok(true),
+                Target::Reject => return Ok(false),
+                Target::Workflow(next) => current = next,
+            }
+        }
+        Err(EvaluateError::Cycle)
+    }
+
+    /// Splits the given ranges into every accepted hyper-rectangle.
+    fn accepted(&self, ranges: Ranges) -> Result<Vec<Ranges>, EvaluateError<'a>> {
+        let walk = self.walk(self.start, ranges, |_, _, _| ());
+        match walk.fall_through.first() {
+            Some(&(workflow, _)) => Err(EvaluateError::NoFallback(self.names[workflow])),
+            None => Ok(walk.accepted),
+        }
+    }
+
+    /// Pushes the ranges through the workflows starting at `start`, calling `visit` with
+    /// the ranges matched by each rule. Paths longer than the number of workflows are cyclic,
+    /// they are never accepted and set the `cyclic` flag.
+    fn walk(
+        &self,
+        start: usize,
+        ranges: Ranges,
+        mut visit: impl FnMut(usize, usize, &Ranges),
+    ) -> Walk {
+        let mut result = Walk::default();
+        let mut stack = vec![(ranges, start, 0, 0)];
+        while let Some((mut ranges, workflow, mut rule, depth)) = stack.pop() {
+            if depth > self.workflows.len() {
+                result.cyclic = true;
+                continue;
+            }
+            loop {
+                let Some(current) = self.workflows[workflow].get(rule) else {
+                    result.fall_through.push((workflow, ranges));
                     break;
+                };
+                let pass = match current.condition {
+                    Some((index, operator, number)) => {
+                        let (pass, fail) = operator.split(ranges[index], number);
+                        let pass = pass.map(|pass| {
+                            let mut new_ranges = ranges.clone();
+                            new_ranges[index] = pass;
+                            visit(workflow, rule, &new_ranges);
+                            new_ranges
+                        });
+                        let mut fail = fail.into_iter();
+                        let Some(first) = fail.next() else {
+                            if let Some(pass) = pass {
+                                self.send(pass, current.target, depth, &mut stack, &mut result);
+                            }
+                            break;
+                        };
+                        for other in fail {
+                            let mut new_ranges = ranges.clone();
+                            new_ranges[index] = other;
+                            stack.push((new_ranges, workflow, rule + 1, depth));
+                        }
+                        ranges[index] = first;
+                        pass
+                    }
+                    None => {
+                        visit(workflow, rule, &ranges);
+                        self.send(ranges, current.target, depth, &mut stack, &mut result);
+                        break;
+                    }
+                };
+                if let Some(pass) = pass {
+                    self.send(pass, current.target, depth, &mut stack, &mut result);
                 }
+                rule += 1;
             }
         }
-        if current_workflow == "A" {
-            result += categories.values().sum::<usize>();
+        result
+    }
+
+    fn send(
+        &self,
+        ranges: Ranges,
+        target: Target,
+        depth: usize,
+        stack: &mut Vec<(Ranges, usize, usize, usize)>,
+        result: &mut Walk,
+    ) {
+        match target {
+            Target::Accept => result.accepted.push(ranges),
+            Target::Reject => (),
+            Target::Workflow(next) => stack.push((ranges, next, 0, depth + 1)),
         }
     }
-    result
 }
 
-fn calculate(
-    workflows: &HashMap<&str, Vec<((char, char, usize), &str)>>,
-    mut ranges: Vec<(usize, usize)>,
-    workflow: &str,
-) -> usize {
-    if workflow == "A" {
-        return ranges
+/// The outcome of pushing ranges through the workflows.
+#[derive(Debug, Default)]
+struct Walk {
+    accepted: Vec<Ranges>,
+    /// Ranges that matched no rule of a workflow without a fallback.
+    fall_through: Vec<(usize, Ranges)>,
+    cyclic: bool,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Report<'a> {
+    undefined: Vec<(&'a str, &'a str)>,
+    unreachable_workflows: Vec<&'a str>,
+    unreachable_rules: Vec<(&'a str, usize)>,
+    always_accept: Vec<&'a str>,
+    always_reject: Vec<&'a str>,
+    cycles: Vec<Vec<&'a str>>,
+    no_fallback: Vec<&'a str>,
+}
+impl Display for Report<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let undefined = self
+            .undefined
             .iter()
-            .fold(1, |acc, (start, end)| acc * (end - start + 1));
+            .map(|(from, to)| format!("{} -> {}", from, to));
+        let rules = self
+            .unreachable_rules
+            .iter()
+            .map(|(name, rule)| format!("{}#{}", name, rule));
+        let cycles = self.cycles.iter().map(|x| format!("[{}]", x.join(" ")));
+        writeln!(f, "Undefined references: {}", undefined.format(", "))?;
+        writeln!(
+            f,
+            "Unreachable workflows: {}",
+            self.unreachable_workflows.join(", ")
+        )?;
+        writeln!(f, "Unreachable rules: {}", rules.format(", "))?;
+        writeln!(f, "Always accept: {}", self.always_accept.join(", "))?;
+        writeln!(f, "Always reject: {}", self.always_reject.join(", "))?;
+        writeln!(f, "Cycles: {}", cycles.format(", "))?;
+        writeln!(f, "No fallback: {}", self.no_fallback.join(", "))
     }
-    if workflow == "R" {
-        return 0;
+}
+
+/// Replaces references to undefined workflows with `R` and returns them separately.
+fn sanitise<'a>(workflows: &[Workflow<'a>]) -> (Vec<Workflow<'a>>, Vec<(&'a str, &'a str)>) {
+    let names = workflows.iter().map(|x| x.name).collect::<HashSet<_>>();
+    let mut undefined = vec![];
+    let mut workflows = workflows.to_vec();
+    for workflow in workflows.iter_mut() {
+        for rule in workflow.rules.iter_mut() {
+            if !matches!(rule.destination, "A" | "R") && !names.contains(rule.destination) {
+                undefined.push((workflow.name, rule.destination));
+                rule.destination = "R";
+            }
+        }
     }
-    let mut result = 0;
-    for ((key, operation, number), destination) in workflows.get(workflow).unwrap() {
-        if key == &'.' {
-            result += calculate(workflows, ranges, &destination);
-            break;
-        }
-        let index = match key {
-            'x' => 0,
-            'm' => 1,
-            'a' => 2,
-            's' => 3,
-            _ => unreachable!(),
-        };
-        let mut new_range = ranges.clone();
-        match operation {
-            '<' => {
-                new_range[index].1 = number - 1;
-                ranges[index].0 = *number;
+    (workflows, undefined)
+}
+
+/// Counts the combinations covered by the given hyper-rectangles.
+fn volume(ranges: &[Ranges]) -> usize {
+    ranges
+        .iter()
+        .map(|x| {
+            x.iter()
+                .fold(1, |acc, (start, end)| acc * (end - start + 1))
+        })
+        .sum()
+}
+
+fn analyse<'a>(
+    workflows: &[Workflow<'a>],
+    categories: &[&'a str],
+    start: &str,
+) -> Result<Report<'a>, String> {
+    let (workflows, undefined) = sanitise(workflows);
+    let compiled = Compiled::new(&workflows, categories, start)?;
+    let full = vec![(1, 4000); compiled.categories.len()];
+    let full_volume = volume(std::slice::from_ref(&full));
+    let mut report = Report {
+        undefined,
+        ..Default::default()
+    };
+
+    let mut visited = HashSet::new();
+    let walk = compiled.walk(compiled.start, full.clone(), |workflow, rule, _| {
+        visited.insert((workflow, rule));
+    });
+    report.no_fallback = walk
+        .fall_through
+        .iter()
+        .map(|&(workflow, _)| workflows[workflow].name)
+        .unique()
+        .collect();
+    for (i, workflow) in workflows.iter().enumerate() {
+        if (0..workflow.rules.len()).all(|rule| !visited.contains(&(i, rule))) {
+            report.unreachable_workflows.push(workflow.name);
+            continue;
+        }
+        for rule in 0..workflow.rules.len() {
+            if !visited.contains(&(i, rule)) {
+                report.unreachable_rules.push((workflow.name, rule));
             }
-            '>' => {
-                new_range[index].0 = number + 1;
-                ranges[index].1 = *number;
+        }
+    }
+
+    for (i, workflow) in workflows.iter().enumerate() {
+        let walk = compiled.walk(i, full.clone(), |_, _, _| ());
+        if walk.cyclic || !walk.fall_through.is_empty() {
+            continue;
+        }
+        match volume(&walk.accepted) {
+            0 => report.always_reject.push(workflow.name),
+            x if x == full_volume => report.always_accept.push(workflow.name),
+            _ => (),
+        }
+    }
+
+    let reach = compiled
+        .workflows
+        .iter()
+        .map(|rules| {
+            let mut seen = vec![false; compiled.workflows.len()];
+            let mut stack = rules.iter().map(|x| x.target).collect::<Vec<_>>();
+            while let Some(target) = stack.pop() {
+                if let Target::Workflow(next) = target {
+                    if !seen[next] {
+                        seen[next] = true;
+                        stack.extend(compiled.workflows[next].iter().map(|x| x.target));
+                    }
+                }
             }
-            _ => unreachable!(),
+            seen
+        })
+        .collect::<Vec<_>>();
+    let mut in_cycle = vec![false; workflows.len()];
+    for i in 0..workflows.len() {
+        if !reach[i][i] || in_cycle[i] {
+            continue;
+        }
+        let cycle = (0..workflows.len())
+            .filter(|&j| reach[i][j] && reach[j][i])
+            .collect::<Vec<_>>();
+        cycle.iter().for_each(|&j| in_cycle[j] = true);
+        report
+            .cycles
+            .push(cycle.iter().map(|&j| workflows[j].name).collect());
+    }
+    Ok(report)
+}
+
+fn resolve<'a>(aliases: &HashMap<&'a str, &'a str>, mut name: &'a str) -> &'a str {
+    for _ in 0..aliases.len() {
+        match aliases.get(name) {
+            Some(&alias) if alias != name => name = alias,
+            _ => break,
         }
-        result += calculate(workflows, new_range, &destination)
     }
-    result
+    name
 }
+
+/// Builds an equivalent workflow set without the unreachable parts, constant workflows
+/// and rules that end up in the same place as the fallback.
+fn simplify<'a>(workflows: &[Workflow<'a>], report: &Report<'a>, start: &str) -> Vec<Workflow<'a>> {
+    let (workflows, _) = sanitise(workflows);
+    let mut aliases = HashMap::new();
+    for &name in report.always_accept.iter() {
+        aliases.insert(name, "A");
+    }
+    for &name in report.always_reject.iter() {
+        aliases.insert(name, "R");
+    }
+    let mut workflows = workflows
+        .into_iter()
+        .filter(|x| !report.unreachable_workflows.contains(&x.name))
+        .map(|workflow| {
+            let fallback = workflow.rules.len().checked_sub(1);
+            let had_fallback = workflow.rules.last().is_some_and(|x| x.condition.is_none());
+            let mut rules = workflow
+                .rules
+                .into_iter()
+                .enumerate()
+                .filter(|(i, _)| !report.unreachable_rules.contains(&(workflow.name, *i)))
+                .map(|(_, rule)| rule)
+                .collect::<Vec<_>>();
+            // Nothing gets past the last reachable rule only if an unreachable fallback
+            // followed it, without a fallback the unmatched ranges must still fall through.
+            if had_fallback
+                && fallback.is_some_and(|x| report.unreachable_rules.contains(&(workflow.name, x)))
+            {
+                if let Some(last) = rules.last_mut() {
+                    last.condition = None;
+                }
+            }
+            Workflow {
+                name: workflow.name,
+                rules,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for workflow in workflows.iter_mut() {
+            for rule in workflow.rules.iter_mut() {
+                let destination = resolve(&aliases, rule.destination);
+                if destination != rule.destination {
+                    rule.destination = destination;
+                    changed = true;
+                }
+            }
+            while workflow.rules.len() > 1
+                && workflow.rules[workflow.rules.len() - 1].condition.is_none()
+            {
+                let fallback = workflow.rules[workflow.rules.len() - 1].destination;
+                if workflow.rules[workflow.rules.len() - 2].destination != fallback {
+                    break;
+                }
+                workflow.rules.remove(workflow.rules.len() - 2);
+                changed = true;
+            }
+            if workflow.name != start
+                && workflow.rules.len() == 1
+                && !aliases.contains_key(workflow.name)
+                && resolve(&aliases, workflow.rules[0].destination) != workflow.name
+            {
+                aliases.insert(workflow.name, workflow.rules[0].destination);
+                changed = true;
+            }
+        }
+    }
+
+    let mut used = HashSet::from([start]);
+    let mut stack = vec![start];
+    while let Some(name) = stack.pop() {
+        if let Some(workflow) = workflows.iter().find(|x| x.name == name) {
+            for rule in workflow.rules.iter() {
+                if used.insert(rule.destination) {
+                    stack.push(rule.destination);
+                }
+            }
+        }
+    }
+    workflows.retain(|x| used.contains(x.name));
+    workflows
+}
+
+fn categories<'a>(parts: &[HashMap<&'a str, usize>]) -> Vec<&'a str> {
+    let mut categories = parts
+        .iter()
+        .flat_map(|x| x.keys().copied())
+        .collect::<Vec<_>>();
+    categories.sort_unstable();
+    categories.dedup();
+    categories
+}
+
 #[timed]
-fn part2(input: &str) -> usize {
-    let (workflows, _) = parse(input);
-    let ranges = vec![(1, 4000), (1, 4000), (1, 4000), (1, 4000)];
-    calculate(&workflows, ranges, "in")
+fn part1(input: &str) -> Result<usize, String> {
+    let (workflows, parts) = parse(input);
+    let compiled = Compiled::new(&workflows, &categories(&parts), "in")?;
+    let mut result = 0;
+    for part in parts.iter() {
+        if compiled.evaluate(part).map_err(|x| x.to_string())? {
+            result += part.values().sum::<usize>();
+        }
+    }
+    Ok(result)
+}
+
+#[timed]
+fn part2(input: &str) -> Result<usize, String> {
+    let (workflows, parts) = parse(input);
+    let compiled = Compiled::new(&workflows, &categories(&parts), "in")?;
+    let ranges = vec![(1, 4000); compiled.categories.len()];
+    let accepted = compiled.accepted(ranges).map_err(|x| x.to_string())?;
+    Ok(volume(&accepted))
 }
 
 #[cfg(test)]
@@ -161,15 +693,129 @@ mod tests {
 
     #[test]
     fn part1_test() {
-        let expected = 19114;
+        let expected = Ok(19114);
         let result = part1(&get_test_input(InputType::Test));
         assert_eq!(result, expected);
     }
 
     #[test]
     fn part2_test() {
-        let expected = 167409079868000;
+        let expected = Ok(167409079868000);
         let result = part2(&get_test_input(InputType::Test));
         assert_eq!(result, expected);
     }
+
+    #[test]
+    fn operators_test() {
+        let input = "in{cool<=10:A,cool>=20:A,shiny=15:A,R}\n\n{cool=10,shiny=1}\n{cool=11,shiny=15}\n{cool=11,shiny=14}";
+        let (workflows, parts) = parse(input);
+        assert_eq!(
+            workflows[0].rules[0].condition,
+            Some(Condition {
+                category: "cool",
+                operator: Operator::LessEqual,
+                number: 10
+            })
+        );
+        let compiled = Compiled::new(&workflows, &categories(&parts), "in").unwrap();
+        let result = parts
+            .iter()
+            .map(|x| compiled.evaluate(x).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(result, vec![true, true, false]);
+
+        let accepted = compiled.accepted(vec![(1, 30), (1, 30)]).unwrap();
+        assert_eq!(volume(&accepted), (10 + 11) * 30 + 9);
+    }
+
+    #[test]
+    fn undefined_workflow_test() {
+        let (workflows, _) = parse("in{x<5:foo,R}");
+        let result = Compiled::new(&workflows, &[], "in");
+        assert_eq!(result.unwrap_err(), "undefined workflow `foo`");
+    }
+
+    #[test]
+    fn analyse_test() {
+        let input = get_test_input(InputType::Test);
+        let (workflows, parts) = parse(&input);
+        let report = analyse(&workflows, &categories(&parts), "in").unwrap();
+        assert_eq!(report.always_accept, vec!["lnx", "qs"]);
+        assert_eq!(report.always_reject, vec!["gd"]);
+        assert!(report.undefined.is_empty());
+        assert!(report.cycles.is_empty());
+        assert!(report.unreachable_rules.is_empty());
+
+        let simplified = simplify(&workflows, &report, "in");
+        assert!(simplified
+            .iter()
+            .all(|x| !matches!(x.name, "lnx" | "qs" | "gd")));
+        let (_, parts_text) = input.split_once("\n\n").unwrap();
+        let simplified_input = format!("{}\n\n{}", simplified.iter().join("\n"), parts_text);
+        assert_eq!(part1(&simplified_input), part1(&input));
+        assert_eq!(part2(&simplified_input), part2(&input));
+    }
+
+    #[test]
+    fn analyse_problems_test() {
+        let input =
+            "in{x<10:a,x<5:b,x>=10:c,R}\na{x>3:b,missing}\nb{m<1:A,R}\nc{x>1:c2,A}\nc2{c}\nd{A}";
+        let (workflows, _) = parse(input);
+        let report = analyse(&workflows, &["x", "m"], "in").unwrap();
+        assert_eq!(report.undefined, vec![("a", "missing")]);
+        assert_eq!(report.unreachable_workflows, vec!["d"]);
+        assert_eq!(
+            report.unreachable_rules,
+            vec![("in", 1), ("in", 3), ("b", 0), ("c", 1)]
+        );
+        assert_eq!(report.always_accept, vec!["d"]);
+        assert_eq!(report.always_reject, vec!["a", "b"]);
+        assert_eq!(report.cycles, vec![vec!["c", "c2"]]);
+
+        let simplified = simplify(&workflows, &report, "in").iter().join("\n");
+        assert_eq!(simplified, "in{x<10:R,c2}\nc2{c2}");
+    }
+
+    #[test]
+    fn simplify_without_fallback_test() {
+        let input = "in{x<10:A,x>20:R,x<5:A}\n\n{x=1}\n{x=30}";
+        let (workflows, parts) = parse(input);
+        let report = analyse(&workflows, &categories(&parts), "in").unwrap();
+        assert_eq!(report.unreachable_rules, vec![("in", 2)]);
+        assert_eq!(report.no_fallback, vec!["in"]);
+        assert!(report.always_accept.is_empty() && report.always_reject.is_empty());
+
+        let simplified = simplify(&workflows, &report, "in").iter().join("\n");
+        assert_eq!(simplified, "in{x<10:A,x>20:R}");
+        let simplified_input = format!("{}\n\n{{x=1}}\n{{x=30}}", simplified);
+        assert_eq!(part1(&simplified_input), part1(input));
+        assert_eq!(
+            part2(input),
+            Err("no rule of workflow `in` matches".to_string())
+        );
+        assert_eq!(part2(&simplified_input), part2(input));
+    }
+
+    #[test]
+    fn evaluate_errors_test() {
+        let input = "in{x<5:A,ab}\nab{x>9:in,x>7:R}\n\n{x=1}\n{x=10}\n{x=6}\n{x=8}\n{m=3}";
+        let (workflows, parts) = parse(input);
+        let compiled = Compiled::new(&workflows, &categories(&parts), "in").unwrap();
+        let result = parts
+            .iter()
+            .map(|x| compiled.evaluate(x))
+            .collect::<Vec<_>>();
+        let expected = vec![
+            Ok(true),
+            Err(EvaluateError::Cycle),
+            Err(EvaluateError::NoFallback("ab")),
+            Ok(false),
+            Err(EvaluateError::MissingCategory("x")),
+        ];
+        assert_eq!(result, expected);
+        assert_eq!(
+            part1(input),
+            Err("part never leaves a cycle of workflows".to_string())
+        );
+    }
 }
@@ -7,6 +7,11 @@ const DAY: u8 = 20;
 
 fn main() {
     let input = read_input(DAY, InputType::Real).unwrap();
+    if std::env::args().any(|arg| arg == "--dot") {
+        let modules = parse(&input);
+        print!("{}", to_dot(&modules, &find_counters(&modules, "rx")));
+        return;
+    }
 
     println!("Part1: {}", part1(&input));
     println!("Part2: {}", part2(&input));
@@ -138,10 +143,135 @@ fn lcm(a: usize, b: usize) -> usize {
     (a * b) / gcd(a, b)
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Counter<'a> {
+    hub: &'a str,
+    output: &'a str,
+    bits: Vec<&'a str>,
+    period: usize,
+}
+
+fn decode_counter<'a>(
+    modules: &HashMap<&'a str, Module<'a>>,
+    start: &'a str,
+) -> Option<Counter<'a>> {
+    let mut bits = vec![];
+    let mut current = start;
+    loop {
+        let module = modules.get(current)?;
+        if module.mod_type != '%' || bits.contains(&current) {
+            return None;
+        }
+        bits.push(current);
+        match module
+            .outputs
+            .iter()
+            .find(|name| modules.get(*name).is_some_and(|x| x.mod_type == '%'))
+        {
+            Some(next) => current = next,
+            None => break,
+        }
+    }
+    let mut hubs = bits
+        .iter()
+        .flat_map(|name| modules.get(name).unwrap().outputs.iter())
+        .filter(|name| modules.get(*name).is_some_and(|x| x.mod_type == '&'))
+        .collect::<Vec<_>>();
+    hubs.sort_unstable();
+    hubs.dedup();
+    if hubs.len() != 1 {
+        return None;
+    }
+    let hub = *hubs[0];
+    let output = *modules
+        .get(hub)
+        .unwrap()
+        .outputs
+        .iter()
+        .find(|name| !bits.contains(name))?;
+    let period = bits.iter().enumerate().fold(0, |acc, (i, name)| {
+        if modules.get(name).unwrap().outputs.contains(&hub) {
+            acc | 1 << i
+        } else {
+            acc
+        }
+    });
+    Some(Counter {
+        hub,
+        output,
+        bits,
+        period,
+    })
+}
+
+fn find_counters<'a>(modules: &HashMap<&'a str, Module<'a>>, target: &str) -> Vec<Counter<'a>> {
+    let Some(parent) = modules
+        .get(target)
+        .and_then(|x| x.inputs.first())
+        .and_then(|x| modules.get(x))
+    else {
+        return vec![];
+    };
+    modules
+        .get("broadcaster")
+        .map(|x| x.outputs.clone())
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|start| decode_counter(modules, start))
+        .filter(|counter| parent.inputs.contains(&counter.output))
+        .collect()
+}
+
+fn counters_period(modules: &HashMap<&str, Module>, target: &str) -> Option<usize> {
+    let parent = modules.get(target)?.inputs.first()?;
+    let counters = find_counters(modules, target);
+    let inputs = &modules.get(parent)?.inputs;
+    if counters.is_empty() || counters.len() != inputs.len() {
+        return None;
+    }
+    Some(counters.iter().fold(1, |acc, x| lcm(acc, x.period)))
+}
+
+fn to_dot(modules: &HashMap<&str, Module>, counters: &[Counter]) -> String {
+    let mut names = modules.keys().copied().collect::<Vec<_>>();
+    names.sort_unstable();
+    let mut result = String::from("digraph modules {\n");
+    for counter in counters {
+        result += &format!("    subgraph cluster_{} {{\n", counter.hub);
+        result += &format!(
+            "        label=\"{} period {}\";\n",
+            counter.output, counter.period
+        );
+        for name in counter.bits.iter().chain([&counter.hub]) {
+            result += &format!("        {};\n", name);
+        }
+        result += "    }\n";
+    }
+    for name in names.iter() {
+        let style = match modules.get(name).unwrap().mod_type {
+            '%' => "shape=box, style=filled, fillcolor=lightblue",
+            '&' => "shape=diamond, style=filled, fillcolor=lightpink",
+            'b' => "shape=doublecircle, style=filled, fillcolor=palegreen",
+            _ => "shape=plaintext",
+        };
+        result += &format!("    {} [{}];\n", name, style);
+    }
+    for name in names {
+        for output in modules.get(name).unwrap().outputs.iter() {
+            result += &format!("    {} -> {};\n", name, output);
+        }
+    }
+    result += "}\n";
+    result
+}
+
 #[timed]
 fn part2(input: &str) -> usize {
     //"lk" 3823, "zv" 4051, "sp" 3929, "xt" 3767
     let modules = parse(input);
+    if let Some(result) = counters_period(&modules, "rx") {
+        return result;
+    }
     let last = modules.get("rx").unwrap().inputs[0];
     modules
         .get(last)
@@ -179,4 +309,38 @@ mod tests {
         let result = part1(&get_test_input(InputType::Other("test2")));
         assert_eq!(result, expected);
     }
+
+    #[test]
+    fn counters_test() {
+        let input = get_test_input(InputType::Real);
+        let modules = parse(&input);
+        let mut periods = find_counters(&modules, "rx")
+            .iter()
+            .map(|x| (x.output, x.period))
+            .collect::<Vec<_>>();
+        periods.sort_unstable();
+        let expected = vec![("lk", 3823), ("sp", 3929), ("xt", 3767), ("zv", 4051)];
+        assert_eq!(periods, expected);
+        assert_eq!(part2(&input), 229215609826339);
+    }
+
+    #[test]
+    fn counters_missing_test() {
+        let input = get_test_input(InputType::Other("test-p2"));
+        let modules = parse(&input);
+        assert!(find_counters(&modules, "rx").is_empty());
+        assert_eq!(counters_period(&modules, "rx"), None);
+    }
+
+    #[test]
+    fn dot_test() {
+        let input = get_test_input(InputType::Test);
+        let result = to_dot(&parse(&input), &[]);
+        assert!(result.starts_with("digraph modules {\n"));
+        assert!(result.contains("    broadcaster [shape=doublecircle"));
+        assert!(result.contains("    a [shape=box"));
+        assert!(result.contains("    inv [shape=diamond"));
+        assert!(result.contains("    broadcaster -> a;\n"));
+        assert!(result.ends_with("}\n"));
+    }
 }
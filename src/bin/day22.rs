@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::fmt::{self, Display, Formatter};
 
 use aoc2023::{read_input, InputType};
 use timed::timed;
@@ -7,10 +7,19 @@ const DAY: u8 = 22;
 
 fn main() {
     let input = read_input(DAY, InputType::Real).unwrap();
-    let (p1, p2) = calculate(&input);
-
-    println!("Part1: {}", p1);
-    println!("Part2: {}", p2);
+    if std::env::args().any(|arg| arg == "--settled") {
+        for brick in build(&input).unwrap().0 {
+            println!("{}", brick);
+        }
+        return;
+    }
+    match calculate(&input) {
+        Ok((p1, p2)) => {
+            println!("Part1: {}", p1);
+            println!("Part2: {}", p2);
+        }
+        Err(error) => println!("Failed: {}", error),
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -32,39 +41,31 @@ struct Brick {
 }
 impl Brick {
     fn new(start: Position, end: Position) -> Brick {
-        Brick { start, end }
-    }
-
-    fn intersect(&self, other: &Brick) -> bool {
-        if self.start.z.max(other.start.z) <= self.end.z.min(other.end.z) {
-            if (self.start.x.max(other.start.x) <= self.end.x.min(other.end.x))
-                && (self.start.y.max(other.start.y) <= self.end.y.min(other.end.y))
-            {
-                return true;
-            }
+        Brick {
+            start: Position::new(start.x.min(end.x), start.y.min(end.y), start.z.min(end.z)),
+            end: Position::new(start.x.max(end.x), start.y.max(end.y), start.z.max(end.z)),
         }
-        false
     }
-
-    fn if_fallen(&self) -> Option<Brick> {
-        if self.start.z > 1 {
-            let mut result = self.clone();
-            result.start.z -= 1;
-            result.end.z -= 1;
-            return Some(result);
-        }
-        None
+}
+impl Display for Brick {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{},{},{}~{},{},{}",
+            self.start.x, self.start.y, self.start.z, self.end.x, self.end.y, self.end.z
+        )
     }
 }
 
-fn parse(input: &str) -> Vec<Brick> {
+/// Rejects bricks reaching below `z = 1`, the ground being at `z = 0`.
+fn parse(input: &str) -> Result<Vec<Brick>, String> {
     input
         .lines()
         .map(|line| {
             let tmp = line.split_once('~').unwrap();
             let mut start = tmp.0.split(',').flat_map(|x| x.parse::<usize>());
             let mut end = tmp.1.split(',').flat_map(|x| x.parse::<usize>());
-            Brick::new(
+            let brick = Brick::new(
                 Position::new(
                     start.next().unwrap(),
                     start.next().unwrap(),
@@ -75,50 +76,128 @@ fn parse(input: &str) -> Vec<Brick> {
                     end.next().unwrap(),
                     end.next().unwrap(),
                 ),
-            )
+            );
+            if brick.start.z < 1 {
+                return Err(format!("brick `{}` is below the ground", line));
+            }
+            Ok(brick)
         })
         .collect()
 }
 
-fn drop(bricks: &mut Vec<Brick>) -> usize {
-    let mut changed = HashSet::new();
-    'outer: for i in 0..bricks.len() {
-        loop {
-            let current = &bricks[i];
-            match current.if_fallen() {
-                Some(fallen) => {
-                    for brick in &bricks[..i] {
-                        if fallen.intersect(brick) {
-                            continue 'outer;
-                        }
+/// Drops the bricks in the given order onto an x/y height map, each one in a single step.
+/// Returns the bricks every brick ends up resting on.
+fn settle(bricks: &mut [Brick]) -> Vec<Vec<usize>> {
+    let width = bricks.iter().map(|x| x.end.x + 1).max().unwrap_or(0);
+    let depth = bricks.iter().map(|x| x.end.y + 1).max().unwrap_or(0);
+    let mut heights = vec![(0, None); width * depth];
+    let mut rests_on = vec![];
+    for (i, brick) in bricks.iter_mut().enumerate() {
+        let cells = (brick.start.x..=brick.end.x)
+            .flat_map(|x| (brick.start.y..=brick.end.y).map(move |y| y * width + x))
+            .collect::<Vec<_>>();
+        let top = cells.iter().map(|&cell| heights[cell].0).max().unwrap_or(0);
+        let mut below = cells
+            .iter()
+            .filter(|&&cell| heights[cell].0 == top)
+            .filter_map(|&cell| heights[cell].1)
+            .collect::<Vec<_>>();
+        below.sort_unstable();
+        below.dedup();
+        rests_on.push(below);
+
+        let fall = brick.start.z - (top + 1);
+        brick.start.z -= fall;
+        brick.end.z -= fall;
+        for cell in cells {
+            heights[cell] = (brick.end.z, Some(i));
+        }
+    }
+    rests_on
+}
+
+#[derive(Debug, Clone)]
+struct SupportGraph {
+    supports: Vec<Vec<usize>>,
+    supported_by: Vec<Vec<usize>>,
+}
+impl SupportGraph {
+    /// Expects every brick to rest only on bricks with a lower index.
+    fn new(supported_by: Vec<Vec<usize>>) -> SupportGraph {
+        let mut supports = vec![vec![]; supported_by.len()];
+        for (i, below) in supported_by.iter().enumerate() {
+            for &j in below {
+                supports[j].push(i);
+            }
+        }
+        SupportGraph {
+            supports,
+            supported_by,
+        }
+    }
+
+    fn supports(&self, brick: usize) -> &[usize] {
+        &self.supports[brick]
+    }
+
+    fn supported_by(&self, brick: usize) -> &[usize] {
+        &self.supported_by[brick]
+    }
+
+    /// Number of other bricks falling when each brick is removed. A brick falls exactly
+    /// when the removed one dominates it, the ground being the root of the dominator tree.
+    fn chain_reactions(&self) -> Vec<usize> {
+        let ground = self.supports.len();
+        let mut idom = vec![ground; ground + 1];
+        let mut depth = vec![0; ground + 1];
+        for brick in 0..ground {
+            let mut parents = self.supported_by(brick).iter().copied();
+            let Some(first) = parents.next() else {
+                depth[brick] = 1;
+                continue;
+            };
+            let dominator = parents.fold(first, |mut a, mut b| {
+                while a != b {
+                    if depth[a] >= depth[b] {
+                        a = idom[a];
+                    } else {
+                        b = idom[b];
                     }
-                    bricks[i] = fallen;
-                    changed.insert(i);
                 }
-                None => break,
-            }
+                a
+            });
+            idom[brick] = dominator;
+            depth[brick] = depth[dominator] + 1;
         }
+        let mut result = vec![0; ground + 1];
+        for brick in (0..ground).rev() {
+            result[idom[brick]] += result[brick] + 1;
+        }
+        result.truncate(ground);
+        result
     }
-    changed.len()
+}
+
+fn build(input: &str) -> Result<(Vec<Brick>, SupportGraph), String> {
+    let mut bricks = parse(input)?;
+    bricks.sort_by_key(|x| x.start.z);
+    let graph = SupportGraph::new(settle(&mut bricks));
+    Ok((bricks, graph))
 }
 
 #[timed]
-fn calculate(input: &str) -> (usize, usize) {
-    let mut bricks = parse(input);
-    bricks.sort_by(|a, b| a.start.z.cmp(&b.start.z));
-    drop(&mut bricks);
-    let mut result_p1 = 0;
-    let mut result_p2 = 0;
-    for i in 0..bricks.len() {
-        let mut bricks_new = bricks.clone();
-        bricks_new.remove(i);
-        let changed = drop(&mut bricks_new);
-        result_p2 += changed;
-        if changed == 0 {
-            result_p1 += 1;
-        }
-    }
-    (result_p1, result_p2)
+fn calculate(input: &str) -> Result<(usize, usize), String> {
+    let (_, graph) = build(input)?;
+    let result_p1 = (0..graph.supports.len())
+        .filter(|&brick| {
+            graph
+                .supports(brick)
+                .iter()
+                .all(|&other| graph.supported_by(other).len() > 1)
+        })
+        .count();
+    let result_p2 = graph.chain_reactions().iter().sum();
+    Ok((result_p1, result_p2))
 }
 
 #[cfg(test)]
@@ -132,14 +211,49 @@ mod tests {
     #[test]
     fn part1_test() {
         let expected = 5;
-        let result = calculate(&get_test_input(InputType::Test)).0;
+        let result = calculate(&get_test_input(InputType::Test)).unwrap().0;
         assert_eq!(result, expected);
     }
 
     #[test]
     fn part2_test() {
         let expected = 7;
-        let result = calculate(&get_test_input(InputType::Test)).1;
+        let result = calculate(&get_test_input(InputType::Test)).unwrap().1;
         assert_eq!(result, expected);
     }
+
+    #[test]
+    fn support_graph_test() {
+        let (bricks, graph) = build(&get_test_input(InputType::Test)).unwrap();
+        assert_eq!(bricks.len(), 7);
+        assert_eq!(graph.supports(0), &[1, 2]);
+        assert_eq!(graph.supported_by(3), &[1, 2]);
+        assert_eq!(graph.supports(5), &[6]);
+        assert_eq!(graph.chain_reactions(), vec![6, 0, 0, 0, 0, 1, 0]);
+    }
+
+    #[test]
+    fn settle_test() {
+        let (bricks, _) = build(&get_test_input(InputType::Test)).unwrap();
+        let result = bricks.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+        let expected = vec![
+            "1,0,1~1,2,1",
+            "0,0,2~2,0,2",
+            "0,2,2~2,2,2",
+            "0,0,3~0,2,3",
+            "2,0,3~2,2,3",
+            "0,1,4~2,1,4",
+            "1,1,5~1,1,6",
+        ];
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn ground_level_test() {
+        let result = calculate("1,0,1~1,2,1\n0,0,0~2,0,0");
+        assert_eq!(
+            result,
+            Err("brick `0,0,0~2,0,0` is below the ground".to_string())
+        );
+    }
 }
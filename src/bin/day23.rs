@@ -1,4 +1,5 @@
 use aoc2023::{read_input, InputType};
+use rayon::prelude::*;
 use timed::timed;
 
 const DAY: u8 = 23;
@@ -6,8 +7,14 @@ const DAY: u8 = 23;
 fn main() {
     let input = read_input(DAY, InputType::Real).unwrap();
 
-    println!("Part1: {}", part1(&input));
-    println!("Part2: {}", part2(&input));
+    let (p1, p2) = (part1(&input), part2(&input));
+    if std::env::args().any(|arg| arg == "--route") {
+        let map = parse(&input);
+        println!("{}\n\n{}", p1.render(&map), p2.render(&map));
+    }
+
+    println!("Part1: {}", p1.length);
+    println!("Part2: {}", p2.length);
 }
 
 fn parse(input: &str) -> Vec<Vec<char>> {
@@ -67,69 +74,297 @@ impl Direction {
     }
 }
 
-fn calculate(
-    map: &mut Vec<Vec<char>>,
-    current: (usize, usize),
-    end: (usize, usize),
+const DIRECTIONS: [Direction; 4] = [
+    Direction::Up,
+    Direction::Right,
+    Direction::Down,
+    Direction::Left,
+];
+
+#[derive(Debug, Clone)]
+struct Edge {
+    to: usize,
+    length: usize,
+    cells: Vec<(usize, usize)>,
+}
+
+#[derive(Debug, Clone)]
+struct Graph {
+    nodes: Vec<(usize, usize)>,
+    edges: Vec<Vec<Edge>>,
+    start: usize,
+    end: usize,
+}
+
+/// Path through the graph as the nodes left and the index of the edge taken from each.
+type Steps = Vec<(usize, usize)>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Hike {
+    length: usize,
+    route: Vec<(usize, usize)>,
+}
+impl Hike {
+    fn new(graph: &Graph, (length, steps): (usize, Steps)) -> Hike {
+        let mut route = vec![graph.nodes[graph.start]];
+        for (node, edge) in steps {
+            route.extend(graph.edges[node][edge].cells.iter());
+        }
+        Hike { length, route }
+    }
+
+    fn render(&self, map: &[Vec<char>]) -> String {
+        let mut map = map.to_vec();
+        for &(x, y) in self.route.iter() {
+            map[y][x] = 'O';
+        }
+        map.iter()
+            .map(|line| line.iter().collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+fn moves(map: &[Vec<char>], point: (usize, usize), slopes: bool) -> Vec<(usize, usize)> {
+    let directions = match Direction::from(map[point.1][point.0]) {
+        Some(direction) if slopes => vec![direction],
+        _ => DIRECTIONS.to_vec(),
+    };
+    directions
+        .iter()
+        .filter_map(|dir| dir.apply_dir(point, map[0].len() - 1, map.len() - 1))
+        .filter(|next| map[next.1][next.0] != '#')
+        .collect()
+}
+
+/// Compresses the maze into the junctions connected by the corridors between them.
+/// With `slopes` an edge only exists in the direction the slopes along it allow.
+fn compress(map: &[Vec<char>], slopes: bool) -> Graph {
+    let start = (1, 0);
+    let end = (map[0].len() - 2, map.len() - 1);
+    let mut nodes = vec![start, end];
+    for (y, row) in map.iter().enumerate() {
+        for (x, &tile) in row.iter().enumerate() {
+            if tile != '#' && moves(map, (x, y), false).len() > 2 {
+                nodes.push((x, y));
+            }
+        }
+    }
+    let edges = nodes
+        .iter()
+        .map(|&node| {
+            moves(map, node, slopes)
+                .into_iter()
+                .filter_map(|mut current| {
+                    let mut previous = node;
+                    let mut cells = vec![current];
+                    loop {
+                        if let Some(to) = nodes.iter().position(|&x| x == current) {
+                            let length = cells.len();
+                            return Some(Edge { to, length, cells });
+                        }
+                        let next = moves(map, current, slopes)
+                            .into_iter()
+                            .find(|&x| x != previous)?;
+                        previous = current;
+                        current = next;
+                        cells.push(current);
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    Graph {
+        nodes,
+        edges,
+        start: 0,
+        end: 1,
+    }
+}
+
+/// Longest path over a graph without cycles, by relaxing the edges in topological order.
+/// Returns `None` if the graph has a cycle or the end can't be reached.
+fn longest_dag(graph: &Graph) -> Option<(usize, Steps)> {
+    let mut incoming = vec![0; graph.nodes.len()];
+    graph
+        .edges
+        .iter()
+        .flatten()
+        .for_each(|edge| incoming[edge.to] += 1);
+    let mut queue = (0..graph.nodes.len())
+        .filter(|&x| incoming[x] == 0)
+        .collect::<Vec<_>>();
+    let mut order = vec![];
+    while let Some(current) = queue.pop() {
+        order.push(current);
+        for edge in graph.edges[current].iter() {
+            incoming[edge.to] -= 1;
+            if incoming[edge.to] == 0 {
+                queue.push(edge.to);
+            }
+        }
+    }
+    if order.len() != graph.nodes.len() {
+        return None;
+    }
+
+    let mut distance = vec![None; graph.nodes.len()];
+    let mut parent = vec![None; graph.nodes.len()];
+    distance[graph.start] = Some(0);
+    for current in order {
+        let Some(base) = distance[current] else {
+            continue;
+        };
+        for (i, edge) in graph.edges[current].iter().enumerate() {
+            if distance[edge.to] < Some(base + edge.length) {
+                distance[edge.to] = Some(base + edge.length);
+                parent[edge.to] = Some((current, i));
+            }
+        }
+    }
+    let mut steps = vec![];
+    let mut current = graph.end;
+    while let Some(step) = parent[current] {
+        steps.push(step);
+        current = step.0;
+    }
+    steps.reverse();
+    distance[graph.end].map(|x| (x, steps))
+}
+
+/// Set of visited nodes, one bit per node.
+#[derive(Debug, Clone)]
+struct Visited(Vec<u64>);
+impl Visited {
+    fn new(nodes: usize) -> Visited {
+        Visited(vec![0; nodes.div_ceil(64)])
+    }
+
+    fn contains(&self, node: usize) -> bool {
+        self.0[node / 64] >> (node % 64) & 1 == 1
+    }
+
+    fn toggle(&mut self, node: usize) {
+        self.0[node / 64] ^= 1 << (node % 64);
+    }
+}
+
+/// The only node leading into the end. Once there, any other way would cut the end
+/// off, so the search can go straight to it.
+fn exit_gate(graph: &Graph) -> Option<usize> {
+    let mut gates =
+        (0..graph.edges.len()).filter(|&i| graph.edges[i].iter().any(|x| x.to == graph.end));
+    let gate = gates.next()?;
+    gates.next().is_none().then_some(gate)
+}
+
+fn search(
+    graph: &Graph,
+    gate: Option<usize>,
+    current: usize,
+    visited: &mut Visited,
     distance: usize,
-    part1: bool,
-) -> usize {
-    if current == end {
-        return distance;
-    }
-    let mut result = 0;
-
-    let mut directions = vec![
-        Direction::Up,
-        Direction::Right,
-        Direction::Down,
-        Direction::Left,
-    ];
-    if part1 {
-        if let Some(direction) = Direction::from(map[current.1][current.0]) {
-            directions = vec![direction]
+    steps: &mut Steps,
+    best: &mut Option<(usize, Steps)>,
+) {
+    if current == graph.end {
+        if best.as_ref().is_none_or(|x| distance > x.0) {
+            *best = Some((distance, steps.clone()));
+        }
+        return;
+    }
+    let edges = &graph.edges[current];
+    for (i, edge) in edges.iter().enumerate() {
+        if visited.contains(edge.to) || (gate == Some(current) && edge.to != graph.end) {
+            continue;
         }
+        steps.push((current, i));
+        visited.toggle(edge.to);
+        search(
+            graph,
+            gate,
+            edge.to,
+            visited,
+            distance + edge.length,
+            steps,
+            best,
+        );
+        visited.toggle(edge.to);
+        steps.pop();
     }
+}
 
-    directions.into_iter().for_each(|dir| {
-        if let Some(next) = dir.apply_dir(current, map[0].len() - 1, map.len() - 1) {
-            if map[next.1][next.0] != '#' {
-                let tmp = map[next.1][next.0];
-                if map[next.1][next.0] == '.' || !part1 {
-                    map[next.1][next.0] = '#';
+/// Longest path from start to end, splitting the search between rayon workers once
+/// the frontier of partial paths is large enough.
+fn longest_path(graph: &Graph, parallel: bool) -> Option<(usize, Steps)> {
+    let gate = exit_gate(graph);
+    let mut visited = Visited::new(graph.nodes.len());
+    visited.toggle(graph.start);
+    let mut frontier = vec![(graph.start, visited, 0, vec![])];
+    let mut finished: Option<(usize, Steps)> = None;
+    while parallel && frontier.len() < 64 {
+        let mut next_frontier = vec![];
+        for (current, visited, distance, steps) in frontier {
+            if current == graph.end {
+                if finished.as_ref().is_none_or(|x| distance > x.0) {
+                    finished = Some((distance, steps));
                 }
-                let r = calculate(map, next, end, distance + 1, part1);
-                map[next.1][next.0] = tmp;
-                if r > result {
-                    result = r;
+                continue;
+            }
+            for (i, edge) in graph.edges[current].iter().enumerate() {
+                if visited.contains(edge.to) || (gate == Some(current) && edge.to != graph.end) {
+                    continue;
                 }
+                let mut steps = steps.clone();
+                steps.push((current, i));
+                let mut visited = visited.clone();
+                visited.toggle(edge.to);
+                next_frontier.push((edge.to, visited, distance + edge.length, steps));
             }
         }
-    });
-    result
+        if next_frontier.is_empty() {
+            return finished;
+        }
+        frontier = next_frontier;
+    }
+    frontier
+        .into_par_iter()
+        .filter_map(|(current, mut visited, distance, mut steps)| {
+            let mut best = None;
+            search(
+                graph,
+                gate,
+                current,
+                &mut visited,
+                distance,
+                &mut steps,
+                &mut best,
+            );
+            best
+        })
+        .chain(finished)
+        .max_by_key(|x| x.0)
 }
 
 #[timed]
-fn part1(input: &str) -> usize {
-    let mut map = parse(input);
-    let max_x = map[0].len() - 1;
-    let max_y = map.len() - 1;
-    map[0][1] = '#';
-    calculate(&mut map, (1, 0), (max_x - 1, max_y), 0, true)
+fn part1(input: &str) -> Hike {
+    let map = parse(input);
+    let graph = compress(&map, true);
+    let result = longest_dag(&graph).or_else(|| longest_path(&graph, false));
+    Hike::new(&graph, result.unwrap())
 }
 
 #[timed]
-fn part2(input: &str) -> usize {
-    let mut map = parse(input);
-    let max_x = map[0].len() - 1;
-    let max_y = map.len() - 1;
-    map[0][1] = '#';
-    calculate(&mut map, (1, 0), (max_x - 1, max_y), 0, false)
+fn part2(input: &str) -> Hike {
+    let map = parse(input);
+    let graph = compress(&map, false);
+    Hike::new(&graph, longest_path(&graph, true).unwrap())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use itertools::Itertools;
 
     fn get_test_input(input_type: InputType) -> String {
         read_input(DAY, input_type).unwrap()
@@ -138,14 +373,76 @@ mod tests {
     #[test]
     fn part1_test() {
         let expected = 94;
-        let result = part1(&get_test_input(InputType::Test));
+        let result = part1(&get_test_input(InputType::Test)).length;
         assert_eq!(result, expected);
     }
 
     #[test]
     fn part2_test() {
         let expected = 154;
-        let result = part2(&get_test_input(InputType::Test));
+        let result = part2(&get_test_input(InputType::Test)).length;
         assert_eq!(result, expected);
     }
+
+    #[test]
+    fn compress_test() {
+        let map = parse(&get_test_input(InputType::Test));
+        let graph = compress(&map, false);
+        assert_eq!(graph.nodes.len(), 9);
+        let edge = &graph.edges[graph.start][0];
+        assert_eq!((graph.nodes[edge.to], edge.length), ((3, 5), 15));
+        let dfs = longest_path(&graph, false).unwrap();
+        assert_eq!(dfs.0, longest_path(&graph, true).unwrap().0);
+        let slopes = compress(&map, true);
+        assert_eq!(longest_dag(&slopes).unwrap().0, 94);
+    }
+
+    #[test]
+    fn route_test() {
+        let input = get_test_input(InputType::Test);
+        for hike in [part1(&input), part2(&input)] {
+            assert_eq!(hike.route.len(), hike.length + 1);
+            assert_eq!(hike.route[0], (1, 0));
+            assert_eq!(hike.route[hike.length], (21, 22));
+            assert!(hike
+                .route
+                .windows(2)
+                .all(|x| x[0].0.abs_diff(x[1].0) + x[0].1.abs_diff(x[1].1) == 1));
+            assert!(hike.route.iter().all_unique());
+        }
+    }
+
+    #[test]
+    fn many_junctions_test() {
+        // a line of 100 nodes, every tenth link also has a detour through its own node
+        let mut edges = vec![vec![]; 110];
+        let link = |edges: &mut Vec<Vec<Edge>>, a: usize, b: usize, length: usize| {
+            edges[a].push(Edge {
+                to: b,
+                length,
+                cells: vec![],
+            });
+            edges[b].push(Edge {
+                to: a,
+                length,
+                cells: vec![],
+            });
+        };
+        for i in 0..99 {
+            link(&mut edges, i, i + 1, 1);
+            if i % 10 == 0 {
+                link(&mut edges, i, 100 + i / 10, 2);
+                link(&mut edges, 100 + i / 10, i + 1, 2);
+            }
+        }
+        let graph = Graph {
+            nodes: vec![(0, 0); 110],
+            edges,
+            start: 0,
+            end: 99,
+        };
+        assert_eq!(exit_gate(&graph), Some(98));
+        assert_eq!(longest_path(&graph, false).unwrap().0, 99 + 10 * 3);
+        assert_eq!(longest_path(&graph, true).unwrap().0, 99 + 10 * 3);
+    }
 }
@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+
+/// Hand types from weakest to strongest. Hands longer than five cards count any five or
+/// more of a kind as five of a kind.
+#[derive(Debug, Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum HandType {
+    HighCard,
+    OnePair,
+    TwoPair,
+    ThreeOfAKind,
+    FullHouse,
+    FourOfAKind,
+    FiveOfAKind,
+}
+impl HandType {
+    /// Type of a hand from how often each label appears, largest first.
+    pub fn from_counts(counts: &[usize]) -> HandType {
+        let largest = counts.first().copied().unwrap_or(0);
+        let second = counts.get(1).copied().unwrap_or(0);
+        match (largest, second) {
+            (5.., _) => HandType::FiveOfAKind,
+            (4, _) => HandType::FourOfAKind,
+            (3, 2..) => HandType::FullHouse,
+            (3, _) => HandType::ThreeOfAKind,
+            (2, 2..) => HandType::TwoPair,
+            (2, _) => HandType::OnePair,
+            _ => HandType::HighCard,
+        }
+    }
+}
+
+/// Ordered by type, then card by card, so equal hands hold the same cards.
+#[derive(Debug, Clone, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Hand {
+    pub hand_type: HandType,
+    /// Position of every card in the ruleset's order.
+    pub strengths: Vec<usize>,
+    pub cards: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ruleset {
+    /// Card labels from weakest to strongest.
+    order: Vec<char>,
+    /// Label standing in for whichever card makes the hand strongest.
+    wildcard: Option<char>,
+    hand_size: usize,
+}
+impl Ruleset {
+    pub fn new(order: &str, wildcard: Option<char>, hand_size: usize) -> Result<Ruleset, String> {
+        let order = order.chars().collect::<Vec<_>>();
+        if let Some(label) = order
+            .iter()
+            .enumerate()
+            .find_map(|(i, x)| order[..i].contains(x).then_some(x))
+        {
+            return Err(format!("card `{}` is ranked twice", label));
+        }
+        if let Some(wildcard) = wildcard.filter(|x| !order.contains(x)) {
+            return Err(format!("wildcard `{}` is not a card", wildcard));
+        }
+        Ok(Ruleset {
+            order,
+            wildcard,
+            hand_size,
+        })
+    }
+
+    /// Rules of the first part.
+    pub fn standard() -> Ruleset {
+        Ruleset::new("23456789TJQKA", None, 5).unwrap()
+    }
+
+    /// Rules of the second part, where jokers are wild and the weakest card.
+    pub fn jokers() -> Ruleset {
+        Ruleset::new("J23456789TQKA", Some('J'), 5).unwrap()
+    }
+
+    pub fn hand(&self, cards: &str) -> Result<Hand, String> {
+        let strengths = cards
+            .chars()
+            .map(|x| {
+                self.order
+                    .iter()
+                    .position(|&label| label == x)
+                    .ok_or_else(|| format!("unknown card `{}`", x))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if strengths.len() != self.hand_size {
+            return Err(format!(
+                "hand `{}` does not have {} cards",
+                cards, self.hand_size
+            ));
+        }
+        let mut counts = HashMap::new();
+        let mut wildcards = 0;
+        for label in cards.chars() {
+            if Some(label) == self.wildcard {
+                wildcards += 1;
+            } else {
+                *counts.entry(label).or_insert(0) += 1;
+            }
+        }
+        let mut counts = counts.into_values().collect::<Vec<_>>();
+        counts.sort_unstable_by(|a, b| b.cmp(a));
+        match counts.first_mut() {
+            Some(largest) => *largest += wildcards,
+            None => counts.push(wildcards),
+        }
+        Ok(Hand {
+            hand_type: HandType::from_counts(&counts),
+            strengths,
+            cards: cards.to_string(),
+        })
+    }
+
+    /// Total winnings of lines of a hand and its bid, each bid multiplied by the rank.
+    pub fn winnings(&self, input: &str) -> Result<u64, String> {
+        let mut hands = input
+            .lines()
+            .map(|line| {
+                let (cards, bid) = line
+                    .split_once(' ')
+                    .ok_or_else(|| format!("missing bid in `{}`", line))?;
+                let bid = bid
+                    .parse::<u64>()
+                    .map_err(|_| format!("invalid bid `{}`", bid))?;
+                Ok((self.hand(cards)?, bid))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        hands.sort();
+        Ok((1..).zip(hands).map(|(rank, (_, bid))| rank * bid).sum())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{read_input, InputType};
+
+    #[test]
+    fn hand_type_test() {
+        let standard = Ruleset::standard();
+        let jokers = Ruleset::jokers();
+        for (cards, first, second) in [
+            ("32T3K", HandType::OnePair, HandType::OnePair),
+            ("T55J5", HandType::ThreeOfAKind, HandType::FourOfAKind),
+            ("KK677", HandType::TwoPair, HandType::TwoPair),
+            ("KTJJT", HandType::TwoPair, HandType::FourOfAKind),
+            ("QQQJA", HandType::ThreeOfAKind, HandType::FourOfAKind),
+            ("JJJJJ", HandType::FiveOfAKind, HandType::FiveOfAKind),
+            ("23J45", HandType::HighCard, HandType::OnePair),
+            ("2233J", HandType::TwoPair, HandType::FullHouse),
+        ] {
+            assert_eq!(standard.hand(cards).unwrap().hand_type, first, "{}", cards);
+            assert_eq!(jokers.hand(cards).unwrap().hand_type, second, "{}", cards);
+        }
+    }
+
+    #[test]
+    fn ordering_test() {
+        let jokers = Ruleset::jokers();
+        let (weaker, stronger) = (jokers.hand("JKKK2").unwrap(), jokers.hand("QQQQ2").unwrap());
+        assert!(weaker < stronger);
+        assert_ne!(weaker, stronger);
+        assert_eq!(weaker, jokers.hand("JKKK2").unwrap());
+        let standard = Ruleset::standard();
+        assert!(standard.hand("JKKK2").unwrap() > standard.hand("TKKK2").unwrap());
+        assert_eq!(
+            standard.winnings(&read_input(7, InputType::Test).unwrap()),
+            Ok(6440)
+        );
+        assert_eq!(
+            jokers.winnings(&read_input(7, InputType::Test).unwrap()),
+            Ok(5905)
+        );
+    }
+
+    #[test]
+    fn variant_test() {
+        let ruleset = Ruleset::new("A23456789TJQK", Some('2'), 3).unwrap();
+        assert_eq!(
+            ruleset.hand("22K").unwrap().hand_type,
+            HandType::ThreeOfAKind
+        );
+        assert_eq!(ruleset.hand("A2K").unwrap().hand_type, HandType::OnePair);
+        assert!(ruleset.hand("AK").is_err());
+        assert_eq!(ruleset.winnings("KQJ 1\nAA2 2\nA3K 3"), Ok(3 + 2 + 2 * 3));
+        assert_eq!(ruleset.hand("A1K"), Err("unknown card `1`".to_string()));
+        assert_eq!(
+            Ruleset::new("AKA", None, 5),
+            Err("card `A` is ranked twice".to_string())
+        );
+        assert_eq!(
+            Ruleset::new("AK", Some('J'), 5),
+            Err("wildcard `J` is not a card".to_string())
+        );
+    }
+}
@@ -0,0 +1,318 @@
+use std::fmt::{self, Display, Formatter};
+
+use rayon::prelude::*;
+
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq)]
+pub enum Spring {
+    Operational,
+    Damaged,
+    Unknown,
+}
+impl Spring {
+    pub fn parse(symbol: char) -> Result<Spring, String> {
+        match symbol {
+            '.' => Ok(Spring::Operational),
+            '#' => Ok(Spring::Damaged),
+            '?' => Ok(Spring::Unknown),
+            _ => Err(format!("unknown spring `{}`", symbol)),
+        }
+    }
+
+    pub fn symbol(&self) -> char {
+        match self {
+            Spring::Operational => '.',
+            Spring::Damaged => '#',
+            Spring::Unknown => '?',
+        }
+    }
+}
+
+pub fn render(springs: &[Spring]) -> String {
+    springs.iter().map(|x| x.symbol()).collect()
+}
+
+/// Condition record: the springs and the sizes of the contiguous damaged groups.
+#[derive(Debug, Clone, Eq, Hash, PartialEq)]
+pub struct Row {
+    pub springs: Vec<Spring>,
+    pub groups: Vec<usize>,
+}
+impl Row {
+    pub fn new(springs: Vec<Spring>, groups: Vec<usize>) -> Row {
+        Row { springs, groups }
+    }
+
+    pub fn parse(line: &str) -> Result<Row, String> {
+        let (springs, groups) = line
+            .split_once(' ')
+            .ok_or_else(|| format!("missing groups in `{}`", line))?;
+        let springs = springs
+            .chars()
+            .map(Spring::parse)
+            .collect::<Result<_, _>>()?;
+        let groups = groups
+            .split(',')
+            .map(|x| x.parse().map_err(|_| format!("invalid group `{}`", x)))
+            .collect::<Result<_, _>>()?;
+        Ok(Row { springs, groups })
+    }
+
+    /// Repeats the springs `factor` times joined by unknowns, and the groups `factor` times.
+    pub fn unfold(&self, factor: usize) -> Row {
+        let mut springs = vec![];
+        for i in 0..factor {
+            if i > 0 {
+                springs.push(Spring::Unknown);
+            }
+            springs.extend_from_slice(&self.springs);
+        }
+        Row {
+            springs,
+            groups: self.groups.repeat(factor),
+        }
+    }
+
+    /// `table[i][j]` counts the arrangements of `springs[i..]` holding `groups[j..]`,
+    /// given that a group may start at `i`.
+    fn table(&self, operational: &[usize]) -> Vec<Vec<u128>> {
+        let (length, count) = (self.springs.len(), self.groups.len());
+        let mut table = vec![vec![0u128; count + 1]; length + 2];
+        table[length][count] = 1;
+        table[length + 1][count] = 1;
+        for i in (0..length).rev() {
+            for j in 0..=count {
+                let mut ways = 0;
+                if self.springs[i] != Spring::Damaged {
+                    ways += table[i + 1][j];
+                }
+                if j < count && self.fits(operational, i, self.groups[j]) {
+                    ways += table[i + self.groups[j] + 1][j + 1];
+                }
+                table[i][j] = ways;
+            }
+        }
+        table
+    }
+
+    /// `operational[i]` is the number of operational springs before `i`.
+    fn operational(&self) -> Vec<usize> {
+        let mut operational = vec![0; self.springs.len() + 1];
+        for (i, &spring) in self.springs.iter().enumerate() {
+            operational[i + 1] = operational[i] + (spring == Spring::Operational) as usize;
+        }
+        operational
+    }
+
+    /// Whether a group of `size` can be placed at `start`, followed by a gap or the end.
+    fn fits(&self, operational: &[usize], start: usize, size: usize) -> bool {
+        let end = start + size;
+        end <= self.springs.len()
+            && operational[end] == operational[start]
+            && self.springs.get(end) != Some(&Spring::Damaged)
+    }
+
+    pub fn arrangements(&self) -> u128 {
+        self.table(&self.operational())[0][0]
+    }
+
+    /// Springs every arrangement agrees on, others are left unknown. `None` when the row
+    /// has no arrangement at all.
+    pub fn forced(&self) -> Option<Vec<Spring>> {
+        let operational = self.operational();
+        let table = self.table(&operational);
+        if table[0][0] == 0 {
+            return None;
+        }
+        let (length, count) = (self.springs.len(), self.groups.len());
+        let mut reachable = vec![vec![false; count + 1]; length + 2];
+        reachable[0][0] = true;
+        let mut can_operate = vec![false; length];
+        // damaged[i] - damaged[i - 1] is how many placed groups start or stop covering i
+        let mut damaged = vec![0i64; length + 1];
+        for i in 0..length {
+            for j in 0..=count {
+                if !reachable[i][j] {
+                    continue;
+                }
+                if self.springs[i] != Spring::Damaged && table[i + 1][j] > 0 {
+                    can_operate[i] = true;
+                    reachable[i + 1][j] = true;
+                }
+                if j < count && self.fits(&operational, i, self.groups[j]) {
+                    let end = i + self.groups[j];
+                    if table[end + 1][j + 1] > 0 {
+                        damaged[i] += 1;
+                        damaged[end] -= 1;
+                        if end < length {
+                            can_operate[end] = true;
+                        }
+                        reachable[end + 1][j + 1] = true;
+                    }
+                }
+            }
+        }
+        let mut covered = 0;
+        let result = (0..length)
+            .map(|i| {
+                covered += damaged[i];
+                match (covered > 0, can_operate[i]) {
+                    (true, false) => Spring::Damaged,
+                    (false, true) => Spring::Operational,
+                    _ => Spring::Unknown,
+                }
+            })
+            .collect();
+        Some(result)
+    }
+
+    /// Concrete arrangements, each with every unknown replaced. Only branches leading to
+    /// an arrangement are explored, so this is as slow as the output is long.
+    pub fn enumerate(&self) -> Arrangements<'_> {
+        let operational = self.operational();
+        let table = self.table(&operational);
+        let stack = if table[0][0] > 0 {
+            vec![(0, 0, vec![])]
+        } else {
+            vec![]
+        };
+        Arrangements {
+            row: self,
+            operational,
+            table,
+            stack,
+        }
+    }
+}
+impl Display for Row {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let groups = self
+            .groups
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>();
+        write!(f, "{} {}", render(&self.springs), groups.join(","))
+    }
+}
+
+pub struct Arrangements<'a> {
+    row: &'a Row,
+    operational: Vec<usize>,
+    table: Vec<Vec<u128>>,
+    stack: Vec<(usize, usize, Vec<Spring>)>,
+}
+impl Iterator for Arrangements<'_> {
+    type Item = Vec<Spring>;
+
+    fn next(&mut self) -> Option<Vec<Spring>> {
+        let springs = &self.row.springs;
+        while let Some((i, j, current)) = self.stack.pop() {
+            if i >= springs.len() {
+                return Some(current);
+            }
+            if j < self.row.groups.len() {
+                let size = self.row.groups[j];
+                let end = i + size;
+                if self.row.fits(&self.operational, i, size) && self.table[end + 1][j + 1] > 0 {
+                    let mut next = current.clone();
+                    next.extend(std::iter::repeat_n(Spring::Damaged, size));
+                    if end < springs.len() {
+                        next.push(Spring::Operational);
+                    }
+                    self.stack.push((end + 1, j + 1, next));
+                }
+            }
+            if springs[i] != Spring::Damaged && self.table[i + 1][j] > 0 {
+                let mut next = current;
+                next.push(Spring::Operational);
+                self.stack.push((i + 1, j, next));
+            }
+        }
+        None
+    }
+}
+
+pub fn parse(input: &str) -> Result<Vec<Row>, String> {
+    input.lines().map(Row::parse).collect()
+}
+
+/// Sum of the arrangements of every row unfolded by `factor`, counted in parallel.
+pub fn total_arrangements(rows: &[Row], factor: usize) -> u128 {
+    rows.par_iter()
+        .map(|row| row.unfold(factor).arrangements())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{read_input, InputType};
+
+    #[test]
+    fn arrangements_test() {
+        let rows = parse(&read_input(12, InputType::Test).unwrap()).unwrap();
+        let counts = rows.iter().map(|x| x.arrangements()).collect::<Vec<_>>();
+        assert_eq!(counts, vec![1, 4, 1, 1, 4, 10]);
+        let counts = rows
+            .iter()
+            .map(|x| x.unfold(5).arrangements())
+            .collect::<Vec<_>>();
+        assert_eq!(counts, vec![1, 16384, 1, 16, 2500, 506250]);
+        assert_eq!(total_arrangements(&rows, 1), 21);
+        assert_eq!(rows[1].unfold(0).arrangements(), 1);
+        assert_eq!(rows[5].to_string(), "?###???????? 3,2,1");
+        assert!(Row::parse("?x 1").is_err());
+    }
+
+    #[test]
+    fn enumerate_test() {
+        let row = Row::parse("?###???????? 3,2,1").unwrap();
+        let mut found = row.enumerate().map(|x| render(&x)).collect::<Vec<_>>();
+        found.sort();
+        found.dedup();
+        assert_eq!(found.len(), 10);
+        assert!(found.contains(&".###.##.#...".to_string()));
+        assert!(found.contains(&".###....##.#".to_string()));
+        assert_eq!(Row::parse("#.# 2").unwrap().enumerate().count(), 0);
+
+        for (row, expected) in [
+            ("?###???????? 3,2,1", Some(".###.???????")),
+            ("??????? 4,2", Some("####.##")),
+            ("???????? 4,2", Some("?###??#?")),
+            ("?.?#?? 1,3", Some("#.?##?")),
+            ("#.# 2", None),
+        ] {
+            let forced = Row::parse(row).unwrap().forced().map(|x| render(&x));
+            assert_eq!(forced.as_deref(), expected);
+        }
+        let empty = Row::new(vec![Spring::Unknown; 3], vec![]);
+        assert_eq!(empty.forced().map(|x| render(&x)).as_deref(), Some("..."));
+
+        let rows = parse(&read_input(12, InputType::Real).unwrap()).unwrap();
+        for row in rows.iter().take(50) {
+            let arrangements = row.enumerate().collect::<Vec<_>>();
+            assert_eq!(arrangements.len() as u128, row.arrangements());
+            let agreed = (0..row.springs.len()).map(|i| {
+                let spring = arrangements[0][i];
+                let same = arrangements.iter().all(|x| x[i] == spring);
+                if same {
+                    spring
+                } else {
+                    Spring::Unknown
+                }
+            });
+            assert_eq!(row.forced(), Some(agreed.collect()));
+            for arrangement in arrangements {
+                let groups = arrangement
+                    .split(|&x| x == Spring::Operational)
+                    .map(|x| x.len())
+                    .filter(|&x| x > 0)
+                    .collect::<Vec<_>>();
+                assert_eq!(groups, row.groups);
+                let matches = row.springs.iter().zip(arrangement.iter());
+                assert!(matches
+                    .into_iter()
+                    .all(|(&a, &b)| a == Spring::Unknown || a == b));
+            }
+        }
+    }
+}
@@ -0,0 +1,426 @@
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq)]
+pub enum Direction {
+    Up,
+    Right,
+    Down,
+    Left,
+}
+impl Direction {
+    pub const ALL: [Direction; 4] = [
+        Direction::Up,
+        Direction::Right,
+        Direction::Down,
+        Direction::Left,
+    ];
+
+    pub fn clockwise(&self) -> Direction {
+        Direction::ALL[(*self as usize + 1) % 4]
+    }
+
+    pub fn counter_clockwise(&self) -> Direction {
+        Direction::ALL[(*self as usize + 3) % 4]
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq)]
+pub enum Exit {
+    /// Leaves the tile moving in the direction.
+    Leave(Direction),
+    /// Leaves the given tile instead, moving in the direction.
+    Jump((usize, usize), Direction),
+}
+
+/// Behaviour of a tile for a beam entering it while moving in `direction`.
+pub trait Tile {
+    fn exits(&self, direction: Direction) -> Vec<Exit>;
+}
+
+pub struct Empty;
+impl Tile for Empty {
+    fn exits(&self, direction: Direction) -> Vec<Exit> {
+        vec![Exit::Leave(direction)]
+    }
+}
+
+pub enum Mirror {
+    Slash,
+    Backslash,
+}
+impl Tile for Mirror {
+    fn exits(&self, direction: Direction) -> Vec<Exit> {
+        let vertical = matches!(direction, Direction::Up | Direction::Down);
+        let turn = match (self, vertical) {
+            (Mirror::Slash, true) | (Mirror::Backslash, false) => direction.clockwise(),
+            _ => direction.counter_clockwise(),
+        };
+        vec![Exit::Leave(turn)]
+    }
+}
+
+pub enum Splitter {
+    Vertical,
+    Horizontal,
+}
+impl Tile for Splitter {
+    fn exits(&self, direction: Direction) -> Vec<Exit> {
+        let vertical = matches!(direction, Direction::Up | Direction::Down);
+        match (self, vertical) {
+            (Splitter::Vertical, false) | (Splitter::Horizontal, true) => vec![
+                Exit::Leave(direction.clockwise()),
+                Exit::Leave(direction.counter_clockwise()),
+            ],
+            _ => vec![Exit::Leave(direction)],
+        }
+    }
+}
+
+pub struct Absorber;
+impl Tile for Absorber {
+    fn exits(&self, _direction: Direction) -> Vec<Exit> {
+        vec![]
+    }
+}
+
+/// Lets through only beams moving in its direction.
+pub struct OneWay(pub Direction);
+impl Tile for OneWay {
+    fn exits(&self, direction: Direction) -> Vec<Exit> {
+        if direction == self.0 {
+            vec![Exit::Leave(direction)]
+        } else {
+            vec![]
+        }
+    }
+}
+
+pub struct Rotator {
+    pub clockwise: bool,
+}
+impl Tile for Rotator {
+    fn exits(&self, direction: Direction) -> Vec<Exit> {
+        match self.clockwise {
+            true => vec![Exit::Leave(direction.clockwise())],
+            false => vec![Exit::Leave(direction.counter_clockwise())],
+        }
+    }
+}
+
+/// Sends the beam on from another tile, keeping its direction.
+pub struct Portal(pub (usize, usize));
+impl Tile for Portal {
+    fn exits(&self, direction: Direction) -> Vec<Exit> {
+        vec![Exit::Jump(self.0, direction)]
+    }
+}
+
+pub struct Legend {
+    tiles: HashMap<char, Box<dyn Tile>>,
+}
+impl Legend {
+    pub fn with(mut self, symbol: char, tile: impl Tile + 'static) -> Legend {
+        self.tiles.insert(symbol, Box::new(tile));
+        self
+    }
+}
+impl Default for Legend {
+    fn default() -> Legend {
+        Legend {
+            tiles: HashMap::new(),
+        }
+        .with('.', Empty)
+        .with('/', Mirror::Slash)
+        .with('\\', Mirror::Backslash)
+        .with('|', Splitter::Vertical)
+        .with('-', Splitter::Horizontal)
+    }
+}
+
+/// Beam network of a contraption, compressed to the straight segments between the tiles
+/// that affect beams. Every node is a beam leaving such an element in some direction.
+#[derive(Debug, Clone)]
+pub struct Contraption {
+    width: usize,
+    height: usize,
+    exits: Vec<[Vec<Exit>; 4]>,
+    elements: Vec<Option<usize>>,
+    successors: Vec<Vec<usize>>,
+    component: Vec<usize>,
+    energised: Vec<Vec<u64>>,
+}
+impl Contraption {
+    pub fn new(input: &str) -> Contraption {
+        Contraption::with_legend(input, &Legend::default()).unwrap()
+    }
+
+    pub fn with_legend(input: &str, legend: &Legend) -> Result<Contraption, String> {
+        let lines = input.lines().collect::<Vec<_>>();
+        let height = lines.len();
+        let width = lines.first().map_or(0, |x| x.chars().count());
+        if width == 0 {
+            return Err("empty grid".to_string());
+        }
+        if lines.iter().any(|x| x.chars().count() != width) {
+            return Err("rows of different length".to_string());
+        }
+        let mut exits = vec![];
+        let mut elements = vec![None; width * height];
+        let mut jumps = vec![];
+        for (cell, symbol) in lines.iter().flat_map(|x| x.chars()).enumerate() {
+            let tile = legend
+                .tiles
+                .get(&symbol)
+                .ok_or(format!("unknown tile `{}`", symbol))?;
+            let tile_exits = Direction::ALL.map(|x| tile.exits(x));
+            if Direction::ALL
+                .iter()
+                .zip(tile_exits.iter())
+                .all(|(&x, exits)| exits == &[Exit::Leave(x)])
+            {
+                continue;
+            }
+            for exit in tile_exits.iter().flatten() {
+                if let Exit::Jump((x, y), _) = *exit {
+                    if x >= width || y >= height {
+                        return Err(format!("jump outside the grid to {},{}", x, y));
+                    }
+                    jumps.push(y * width + x);
+                }
+            }
+            elements[cell] = Some(exits.len());
+            exits.push(tile_exits);
+        }
+        for cell in jumps {
+            if elements[cell].is_none() {
+                elements[cell] = Some(exits.len());
+                exits.push(Direction::ALL.map(|x| vec![Exit::Leave(x)]));
+            }
+        }
+        let count = exits.len();
+        let mut contraption = Contraption {
+            width,
+            height,
+            exits,
+            elements,
+            successors: vec![],
+            component: vec![],
+            energised: vec![],
+        };
+
+        let mut segments = vec![vec![]; count * 4];
+        let mut successors = vec![vec![]; count * 4];
+        for cell in 0..width * height {
+            let Some(element) = contraption.elements[cell] else {
+                continue;
+            };
+            for direction in Direction::ALL {
+                let node = element * 4 + direction as usize;
+                let mut cells = vec![cell];
+                if let Some(next) = contraption.step(cell, direction) {
+                    let (trace, next) = contraption.trace(next, direction);
+                    cells.extend(trace);
+                    successors[node] = next;
+                }
+                segments[node] = cells;
+            }
+        }
+        contraption.successors = successors;
+        contraption.condense(&segments);
+        Ok(contraption)
+    }
+
+    fn step(&self, cell: usize, direction: Direction) -> Option<usize> {
+        let (x, y) = (cell % self.width, cell / self.width);
+        match direction {
+            Direction::Up if y > 0 => Some(cell - self.width),
+            Direction::Right if x + 1 < self.width => Some(cell + 1),
+            Direction::Down if y + 1 < self.height => Some(cell + self.width),
+            Direction::Left if x > 0 => Some(cell - 1),
+            _ => None,
+        }
+    }
+
+    fn node(&self, cell: usize, direction: Direction) -> usize {
+        self.elements[cell].unwrap() * 4 + direction as usize
+    }
+
+    /// Nodes a beam entering the element on `cell` continues as.
+    fn enter(&self, cell: usize, direction: Direction) -> Vec<usize> {
+        let element = self.elements[cell].unwrap();
+        self.exits[element][direction as usize]
+            .iter()
+            .map(|exit| match *exit {
+                Exit::Leave(direction) => self.node(cell, direction),
+                Exit::Jump((x, y), direction) => self.node(y * self.width + x, direction),
+            })
+            .collect()
+    }
+
+    /// Follows a beam entering `cell` until it hits an element or leaves the grid.
+    /// Returns the cells it crosses and the nodes it continues as.
+    fn trace(&self, mut cell: usize, direction: Direction) -> (Vec<usize>, Vec<usize>) {
+        let mut cells = vec![cell];
+        while self.elements[cell].is_none() {
+            match self.step(cell, direction) {
+                Some(next) => cell = next,
+                None => return (cells, vec![]),
+            }
+            cells.push(cell);
+        }
+        (cells, self.enter(cell, direction))
+    }
+
+    /// Tarjan's algorithm over the nodes; components come out with everything they reach
+    /// already finished, so each energised set is built once from its successors.
+    fn condense(&mut self, segments: &[Vec<usize>]) {
+        let nodes = self.successors.len();
+        let words = (self.width * self.height).div_ceil(64);
+        let mut index = vec![usize::MAX; nodes];
+        let mut low = vec![0; nodes];
+        let mut on_stack = vec![false; nodes];
+        let mut stack = vec![];
+        let mut component = vec![usize::MAX; nodes];
+        let mut energised: Vec<Vec<u64>> = vec![];
+        let mut counter = 0;
+        for root in 0..nodes {
+            if index[root] != usize::MAX {
+                continue;
+            }
+            let mut work = vec![(root, 0)];
+            while let Some((node, child)) = work.pop() {
+                if child == 0 {
+                    index[node] = counter;
+                    low[node] = counter;
+                    counter += 1;
+                    stack.push(node);
+                    on_stack[node] = true;
+                } else {
+                    let previous = self.successors[node][child - 1];
+                    if on_stack[previous] {
+                        low[node] = low[node].min(low[previous]);
+                    }
+                }
+                if let Some(&next) = self.successors[node].get(child) {
+                    work.push((node, child + 1));
+                    if index[next] == usize::MAX {
+                        work.push((next, 0));
+                    } else if on_stack[next] {
+                        low[node] = low[node].min(index[next]);
+                    }
+                    continue;
+                }
+                if low[node] != index[node] {
+                    continue;
+                }
+                let id = energised.len();
+                let mut members = vec![];
+                while let Some(member) = stack.pop() {
+                    on_stack[member] = false;
+                    component[member] = id;
+                    members.push(member);
+                    if member == node {
+                        break;
+                    }
+                }
+                let mut cells = vec![0u64; words];
+                for &member in members.iter() {
+                    for &cell in segments[member].iter() {
+                        cells[cell / 64] |= 1 << (cell % 64);
+                    }
+                    for &next in self.successors[member].iter() {
+                        if component[next] != id {
+                            cells
+                                .iter_mut()
+                                .zip(energised[component[next]].iter())
+                                .for_each(|(a, b)| *a |= b);
+                        }
+                    }
+                }
+                energised.push(cells);
+            }
+        }
+        self.component = component;
+        self.energised = energised;
+    }
+
+    pub fn energised(&self, (x, y): (usize, usize), direction: Direction) -> usize {
+        let cell = y * self.width + x;
+        let (cells, next) = match self.elements[cell] {
+            Some(_) => (vec![cell], self.enter(cell, direction)),
+            None => self.trace(cell, direction),
+        };
+        let mut result = vec![0u64; (self.width * self.height).div_ceil(64)];
+        for cell in cells {
+            result[cell / 64] |= 1 << (cell % 64);
+        }
+        for node in next {
+            let component = &self.energised[self.component[node]];
+            result.iter_mut().zip(component).for_each(|(a, b)| *a |= b);
+        }
+        result.iter().map(|x| x.count_ones() as usize).sum()
+    }
+
+    pub fn edge_starts(&self) -> Vec<((usize, usize), Direction)> {
+        let mut result = vec![];
+        for x in 0..self.width {
+            result.push(((x, 0), Direction::Down));
+            result.push(((x, self.height - 1), Direction::Up));
+        }
+        for y in 0..self.height {
+            result.push(((0, y), Direction::Right));
+            result.push(((self.width - 1, y), Direction::Left));
+        }
+        result
+    }
+
+    pub fn max_energised(&self) -> usize {
+        self.edge_starts()
+            .into_iter()
+            .map(|(start, direction)| self.energised(start, direction))
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{read_input, InputType};
+
+    #[test]
+    fn energised_test() {
+        let contraption = Contraption::new(&read_input(16, InputType::Test).unwrap());
+        assert_eq!(contraption.energised((0, 0), Direction::Right), 46);
+        assert_eq!(contraption.energised((3, 0), Direction::Down), 51);
+        assert_eq!(contraption.max_energised(), 51);
+    }
+
+    #[test]
+    fn rectangular_test() {
+        let contraption = Contraption::new("...\\\n....\n.-./\n..\\.\n....\n....");
+        assert_eq!(contraption.edge_starts().len(), 2 * 4 + 2 * 6);
+        assert_eq!(contraption.energised((0, 0), Direction::Right), 9);
+        assert_eq!(contraption.energised((1, 5), Direction::Up), 12);
+    }
+
+    #[test]
+    fn legend_test() {
+        let legend = Legend::default()
+            .with('#', Absorber)
+            .with('>', OneWay(Direction::Right))
+            .with('R', Rotator { clockwise: true })
+            .with('P', Portal((0, 2)));
+        let contraption = Contraption::with_legend("..#.\n>.RP\n....", &legend).unwrap();
+        assert_eq!(contraption.energised((0, 0), Direction::Right), 3);
+        assert_eq!(contraption.energised((3, 0), Direction::Down), 3);
+        assert_eq!(contraption.energised((0, 1), Direction::Right), 4);
+        assert_eq!(contraption.energised((1, 1), Direction::Left), 2);
+        assert_eq!(contraption.energised((3, 1), Direction::Left), 2);
+
+        let result = Contraption::with_legend("..x.", &legend);
+        assert_eq!(result.unwrap_err(), "unknown tile `x`");
+        let result = Contraption::with_legend("", &legend);
+        assert_eq!(result.unwrap_err(), "empty grid");
+    }
+}
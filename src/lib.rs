@@ -1,3 +1,9 @@
+pub mod day07;
+pub mod day12;
+pub mod day16;
+pub mod nonogram;
+pub mod polygon;
+
 use std::{
     fmt::{Display, Formatter, Result},
     fs, io,
@@ -0,0 +1,194 @@
+use crate::day12::{render, Row, Spring};
+
+/// Clues of a nonogram, read top to bottom and left to right.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Clues {
+    pub rows: Vec<Vec<usize>>,
+    pub columns: Vec<Vec<usize>>,
+}
+
+pub type Grid = Vec<Vec<Spring>>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Solution {
+    None,
+    Unique(Grid),
+    /// Two of the solutions.
+    Multiple(Grid, Grid),
+}
+
+/// Row clues and column clues as two blocks of comma separated lines, an empty line of
+/// the picture has the clue `0`.
+pub fn parse(input: &str) -> Result<Clues, String> {
+    let lines = input.lines().map(|x| x.trim()).collect::<Vec<_>>();
+    let blocks = lines.split(|x| x.is_empty()).filter(|x| !x.is_empty());
+    let blocks = blocks
+        .map(|block| block.iter().map(|line| parse_clue(line)).collect())
+        .collect::<Result<Vec<Vec<_>>, _>>()?;
+    match <[_; 2]>::try_from(blocks) {
+        Ok([rows, columns]) => Ok(Clues { rows, columns }),
+        Err(blocks) => Err(format!("expected 2 blocks of clues, got {}", blocks.len())),
+    }
+}
+
+fn parse_clue(line: &str) -> Result<Vec<usize>, String> {
+    let clue = line
+        .split(',')
+        .map(|x| {
+            x.trim()
+                .parse()
+                .map_err(|_| format!("invalid clue `{}`", line))
+        })
+        .collect::<Result<Vec<usize>, _>>()?;
+    Ok(clue.into_iter().filter(|&x| x > 0).collect())
+}
+
+pub fn render_grid(grid: &Grid) -> String {
+    grid.iter().map(|x| render(x) + "\n").collect()
+}
+
+fn column(grid: &Grid, x: usize) -> Vec<Spring> {
+    grid.iter().map(|row| row[x]).collect()
+}
+
+/// Line solves every row and column until nothing changes, `false` on a contradiction.
+fn propagate(clues: &Clues, grid: &mut Grid) -> bool {
+    let (width, height) = (clues.columns.len(), clues.rows.len());
+    let mut dirty_rows = vec![true; height];
+    let mut dirty_columns = vec![true; width];
+    while dirty_rows.contains(&true) || dirty_columns.contains(&true) {
+        for y in 0..height {
+            if !std::mem::take(&mut dirty_rows[y]) {
+                continue;
+            }
+            let row = Row::new(grid[y].clone(), clues.rows[y].clone());
+            let Some(forced) = row.forced() else {
+                return false;
+            };
+            for (x, spring) in forced.into_iter().enumerate() {
+                if grid[y][x] != spring {
+                    grid[y][x] = spring;
+                    dirty_columns[x] = true;
+                }
+            }
+        }
+        for x in 0..width {
+            if !std::mem::take(&mut dirty_columns[x]) {
+                continue;
+            }
+            let row = Row::new(column(grid, x), clues.columns[x].clone());
+            let Some(forced) = row.forced() else {
+                return false;
+            };
+            for (y, spring) in forced.into_iter().enumerate() {
+                if grid[y][x] != spring {
+                    grid[y][x] = spring;
+                    dirty_rows[y] = true;
+                }
+            }
+        }
+    }
+    true
+}
+
+/// Collects up to `limit` solutions, branching on the unsolved row or column with the
+/// fewest arrangements left.
+fn search(clues: &Clues, mut grid: Grid, limit: usize, solutions: &mut Vec<Grid>) {
+    if solutions.len() >= limit || !propagate(clues, &mut grid) {
+        return;
+    }
+    let rows =
+        (0..clues.rows.len()).map(|y| (true, y, Row::new(grid[y].clone(), clues.rows[y].clone())));
+    let columns = (0..clues.columns.len()).map(|x| {
+        (
+            false,
+            x,
+            Row::new(column(&grid, x), clues.columns[x].clone()),
+        )
+    });
+    let branch = rows
+        .chain(columns)
+        .filter(|(_, _, row)| row.springs.contains(&Spring::Unknown))
+        .min_by_key(|(_, _, row)| row.arrangements());
+    let Some((horizontal, index, row)) = branch else {
+        solutions.push(grid);
+        return;
+    };
+    for arrangement in row.enumerate() {
+        let mut next = grid.clone();
+        for (i, spring) in arrangement.into_iter().enumerate() {
+            if horizontal {
+                next[index][i] = spring;
+            } else {
+                next[i][index] = spring;
+            }
+        }
+        search(clues, next, limit, solutions);
+        if solutions.len() >= limit {
+            return;
+        }
+    }
+}
+
+pub fn solve(clues: &Clues) -> Solution {
+    let grid = vec![vec![Spring::Unknown; clues.columns.len()]; clues.rows.len()];
+    let mut solutions = vec![];
+    search(clues, grid, 2, &mut solutions);
+    let mut solutions = solutions.into_iter();
+    match (solutions.next(), solutions.next()) {
+        (None, _) => Solution::None,
+        (Some(first), None) => Solution::Unique(first),
+        (Some(first), Some(second)) => Solution::Multiple(first, second),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clues(picture: &str) -> Clues {
+        let grid = picture
+            .lines()
+            .map(|x| x.chars().map(|x| Spring::parse(x).unwrap()).collect())
+            .collect::<Grid>();
+        let clue = |line: Vec<Spring>| {
+            line.split(|&x| x == Spring::Operational)
+                .map(|x| x.len())
+                .filter(|&x| x > 0)
+                .collect::<Vec<_>>()
+        };
+        Clues {
+            rows: grid.iter().map(|x| clue(x.clone())).collect(),
+            columns: (0..grid[0].len()).map(|x| clue(column(&grid, x))).collect(),
+        }
+    }
+
+    #[test]
+    fn solve_test() {
+        let picture = ".##.##.\n#######\n#######\n.#####.\n..###..\n...#...\n";
+        let Solution::Unique(grid) = solve(&clues(picture)) else {
+            panic!("expected a unique solution");
+        };
+        assert_eq!(render_grid(&grid), picture);
+
+        let Solution::Multiple(first, second) = solve(&clues("#.\n.#\n")) else {
+            panic!("expected multiple solutions");
+        };
+        assert_ne!(first, second);
+
+        let contradiction = Clues {
+            rows: vec![vec![2], vec![]],
+            columns: vec![vec![], vec![1]],
+        };
+        assert_eq!(solve(&contradiction), Solution::None);
+    }
+
+    #[test]
+    fn parse_test() {
+        let clues = parse("1,1\n0\n3\n\n1\n2\n1 , 1\n").unwrap();
+        assert_eq!(clues.rows, vec![vec![1, 1], vec![], vec![3]]);
+        assert_eq!(clues.columns, vec![vec![1], vec![2], vec![1, 1]]);
+        assert!(parse("1\n").is_err());
+        assert!(parse("1\n\nx\n").is_err());
+    }
+}
@@ -0,0 +1,239 @@
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+};
+
+pub type Point = (i64, i64);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolygonError {
+    TooFewVertices(usize),
+    NotClosed {
+        start: Point,
+        end: Point,
+    },
+    SelfIntersection {
+        first: (Point, Point),
+        second: (Point, Point),
+    },
+}
+impl Display for PolygonError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            PolygonError::TooFewVertices(count) => {
+                write!(f, "a polygon needs at least 3 vertices, got {}", count)
+            }
+            PolygonError::NotClosed { start, end } => {
+                write!(f, "path starts at {:?} but ends at {:?}", start, end)
+            }
+            PolygonError::SelfIntersection { first, second } => write!(
+                f,
+                "edge {:?} -> {:?} intersects edge {:?} -> {:?}",
+                first.0, first.1, second.0, second.1
+            ),
+        }
+    }
+}
+impl Error for PolygonError {}
+
+/// Orientation in a y-up coordinate system, on a y-down grid it is mirrored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Winding {
+    Clockwise,
+    CounterClockwise,
+}
+
+/// Simple polygon with integer vertices.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Polygon {
+    vertices: Vec<Point>,
+}
+impl Polygon {
+    /// Builds a polygon from a closed path, whose last point has to repeat the first.
+    pub fn new(path: &[Point]) -> Result<Polygon, PolygonError> {
+        let (Some(&start), Some(&end)) = (path.first(), path.last()) else {
+            return Err(PolygonError::TooFewVertices(0));
+        };
+        if start != end {
+            return Err(PolygonError::NotClosed { start, end });
+        }
+        let mut vertices = path[..path.len() - 1].to_vec();
+        vertices.dedup();
+        if vertices.len() > 1 && vertices.first() == vertices.last() {
+            vertices.pop();
+        }
+        if vertices.len() < 3 {
+            return Err(PolygonError::TooFewVertices(vertices.len()));
+        }
+        let polygon = Polygon { vertices };
+        polygon.check_intersections()?;
+        Ok(polygon)
+    }
+
+    pub fn vertices(&self) -> &[Point] {
+        &self.vertices
+    }
+
+    fn edges(&self) -> impl Iterator<Item = (Point, Point)> + '_ {
+        self.vertices
+            .iter()
+            .zip(self.vertices.iter().cycle().skip(1))
+            .map(|(&a, &b)| (a, b))
+    }
+
+    /// Sweeps the edges ordered by their lowest x, so only edges overlapping in x are
+    /// compared. Neighbouring edges may only share their common vertex.
+    fn check_intersections(&self) -> Result<(), PolygonError> {
+        let edges = self.edges().collect::<Vec<_>>();
+        let count = edges.len();
+        let mut order = (0..count).collect::<Vec<_>>();
+        order.sort_by_key(|&i| edges[i].0 .0.min(edges[i].1 .0));
+        for (position, &i) in order.iter().enumerate() {
+            let (a, b) = edges[i];
+            let max_x = a.0.max(b.0);
+            for &j in order[position + 1..].iter() {
+                let (c, d) = edges[j];
+                if c.0.min(d.0) > max_x {
+                    break;
+                }
+                let intersect = if (i + 1) % count == j {
+                    overlaps(a, b, d)
+                } else if (j + 1) % count == i {
+                    overlaps(c, d, b)
+                } else {
+                    segments_intersect((a, b), (c, d))
+                };
+                if intersect {
+                    let (first, second) = (edges[i.min(j)], edges[i.max(j)]);
+                    return Err(PolygonError::SelfIntersection { first, second });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Twice the signed area from the shoelace formula, positive when counter-clockwise.
+    pub fn signed_double_area(&self) -> i64 {
+        self.edges().map(|(a, b)| a.0 * b.1 - b.0 * a.1).sum()
+    }
+
+    pub fn signed_area(&self) -> f64 {
+        self.signed_double_area() as f64 / 2.0
+    }
+
+    pub fn winding(&self) -> Winding {
+        if self.signed_double_area() > 0 {
+            Winding::CounterClockwise
+        } else {
+            Winding::Clockwise
+        }
+    }
+
+    /// Lattice points lying on the edges.
+    pub fn boundary_points(&self) -> u64 {
+        self.edges()
+            .map(|(a, b)| gcd(a.0.abs_diff(b.0), a.1.abs_diff(b.1)))
+            .sum()
+    }
+
+    /// Lattice points strictly inside, from Pick's theorem.
+    pub fn interior_points(&self) -> u64 {
+        (self.signed_double_area().unsigned_abs() + 2 - self.boundary_points()) / 2
+    }
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+fn cross(o: Point, a: Point, b: Point) -> i128 {
+    (a.0 - o.0) as i128 * (b.1 - o.1) as i128 - (a.1 - o.1) as i128 * (b.0 - o.0) as i128
+}
+
+fn on_segment((a, b): (Point, Point), p: Point) -> bool {
+    cross(a, b, p) == 0
+        && a.0.min(b.0) <= p.0
+        && p.0 <= a.0.max(b.0)
+        && a.1.min(b.1) <= p.1
+        && p.1 <= a.1.max(b.1)
+}
+
+/// Whether the edges a -> b and b -> c fold back onto each other.
+fn overlaps(a: Point, b: Point, c: Point) -> bool {
+    let dot = (a.0 - b.0) as i128 * (c.0 - b.0) as i128 + (a.1 - b.1) as i128 * (c.1 - b.1) as i128;
+    cross(b, a, c) == 0 && dot > 0
+}
+
+fn segments_intersect(first: (Point, Point), second: (Point, Point)) -> bool {
+    let (a, b) = first;
+    let (c, d) = second;
+    let d1 = cross(c, d, a).signum();
+    let d2 = cross(c, d, b).signum();
+    let d3 = cross(a, b, c).signum();
+    let d4 = cross(a, b, d).signum();
+    if d1 * d2 < 0 && d3 * d4 < 0 {
+        return true;
+    }
+    on_segment(second, a) || on_segment(second, b) || on_segment(first, c) || on_segment(first, d)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn square_test() {
+        let polygon = Polygon::new(&[(0, 0), (4, 0), (4, 4), (0, 4), (0, 0)]).unwrap();
+        assert_eq!(polygon.signed_double_area(), 32);
+        assert_eq!(polygon.winding(), Winding::CounterClockwise);
+        assert_eq!(polygon.boundary_points(), 16);
+        assert_eq!(polygon.interior_points(), 9);
+
+        let polygon = Polygon::new(&[(0, 0), (0, 3), (3, 0), (0, 0)]).unwrap();
+        assert_eq!(polygon.signed_area(), -4.5);
+        assert_eq!(polygon.winding(), Winding::Clockwise);
+        assert_eq!(polygon.boundary_points(), 9);
+        assert_eq!(polygon.interior_points(), 1);
+    }
+
+    #[test]
+    fn empty_interior_test() {
+        let polygon = Polygon::new(&[(0, 0), (2, 0), (2, 1), (0, 1), (0, 0)]).unwrap();
+        assert_eq!(polygon.signed_double_area(), 4);
+        assert_eq!(polygon.boundary_points(), 6);
+        assert_eq!(polygon.interior_points(), 0);
+
+        let polygon = Polygon::new(&[(0, 0), (1, 0), (1, 1), (0, 1), (0, 0)]).unwrap();
+        assert_eq!(polygon.interior_points(), 0);
+        let polygon = Polygon::new(&[(0, 0), (5, 0), (5, 1), (1, 1), (1, 3), (0, 3), (0, 0)]);
+        assert_eq!(polygon.unwrap().interior_points(), 0);
+    }
+
+    #[test]
+    fn errors_test() {
+        assert_eq!(
+            Polygon::new(&[(0, 0), (2, 0), (2, 2)]),
+            Err(PolygonError::NotClosed {
+                start: (0, 0),
+                end: (2, 2)
+            })
+        );
+        assert_eq!(
+            Polygon::new(&[(0, 0), (2, 0), (0, 0)]),
+            Err(PolygonError::TooFewVertices(2))
+        );
+        let bow_tie = Polygon::new(&[(0, 0), (2, 2), (2, 0), (0, 2), (0, 0)]);
+        assert_eq!(
+            bow_tie.unwrap_err().to_string(),
+            "edge (0, 0) -> (2, 2) intersects edge (2, 0) -> (0, 2)"
+        );
+        let touching = Polygon::new(&[(0, 0), (4, 0), (4, 2), (2, 0), (2, 4), (0, 4), (0, 0)]);
+        assert!(touching.is_err());
+        let folded = Polygon::new(&[(0, 0), (4, 0), (2, 0), (2, 2), (0, 0)]);
+        assert!(folded.is_err());
+    }
+}